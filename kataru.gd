@@ -13,14 +13,58 @@ extends Node
 
 enum DebugLevel { NONE, INFO, VERBOSE }
 
+# Languages to generate story constants for. Mirrors `codegen.rs`.
+enum CodegenTarget { GDSCRIPT = 1, CSHARP = 2, RUST = 4 }
+
+# What sets off an autosave. Mirrors `autosave.rs`.
+enum AutosaveTrigger { PASSAGE = 1, CHOICE = 2, LINES = 4 }
+
+# Kinds of errors returned by calls into Kataru. Mirrors `status.rs`.
+enum ErrorKind { NONE, UNINITIALIZED, PARSE, VALIDATION, IO, RUNTIME }
+
+# Which lines skipping passes: none, only ones read before, or all. Mirrors `skip.rs`.
+enum SkipMode { OFF, READ, ALL }
+
 # Constants to be configured.
 @export var root_path = "res://kataru"
 @export var story_path = "res://kataru/story"
 @export var compiled_story_path = "res://kataru/story.bin"
 @export var bookmark_path = "user://kataru-bookmark.yml"
+# Directory that save slots are kept in.
+@export var slots_path = "user://kataru-slots"
+# Autosave the main runner to slots named `autosave-0`, `autosave-1`, ... when any of these happen.
+@export_flags("Passage change", "Choice", "Every N lines") var autosave_triggers = 0
+# Lines of dialogue between autosaves with `AutosaveTrigger.LINES`.
+@export var autosave_every_lines = 20
+# How many autosave slots to rotate through before replacing the oldest.
+@export var autosave_slots = 3
+# Lines the player has read, shared by every save.
+@export var read_lines_path = "user://kataru-read.json"
+# YAML file of passage and state renames applied to saves from older versions of the story.
+@export var migrations_path = "res://kataru/migrations.yml"
 @export var default_passage = ""
 @export var debug_level = DebugLevel.INFO
+# Seconds to wait after the last story file change before recompiling.
 @export var watch_poll_interval = 0.5
+# Extra languages to generate story constants for. GDScript constants are always generated.
+@export_flags("GDScript", "C#", "Rust") var codegen_targets = CodegenTarget.GDSCRIPT
+@export var persist_history = false
+# Passage to restart from when a hot reload removes the current passage. Defaults to `default_passage`.
+@export var reload_fallback_passage = ""
+# Whether commands without a registered function are reported as errors.
+@export var strict_commands = false
+# Whether dialogue advances on its own once a pending command completes.
+@export var advance_after_commands = false
+# Auto-play shows each line for its length at this reading speed...
+@export var autoplay_chars_per_second = 30.0
+# ...but for at least this many seconds.
+@export var autoplay_min_line_time = 1.0
+# CSV file to extract dialogue and choices to for translation, e.g. "res://kataru/strings.csv".
+@export var strings_path = ""
+# The locale the story is written in.
+@export var source_locale = "en"
+# The locale to translate dialogue and choices to. Defaults to the project's locale.
+@export var locale = ""
 
 const CODEGEN_PATH = "res://addons/kataru/consts"
 const TEMPLATE_PATH = "res://addons/kataru/consts/template.yml"
@@ -29,7 +73,9 @@ const Directories = preload("res://addons/kataru/directories.gd")
 const Characters = preload("res://addons/kataru/consts/characters.gd")
 const Passages = preload("res://addons/kataru/consts/passages.gd")
 const Namespaces = preload("res://addons/kataru/consts/namespaces.gd")
-var Commands = preload("res://addons/kataru/consts/commands.gd").new()
+const State = preload("res://addons/kataru/consts/state.gd")
+const Attributes = preload("res://addons/kataru/consts/attributes.gd")
+const Commands = preload("res://addons/kataru/consts/commands.gd")
 
 # Interface with Rust.
 var ffi = KataruInterface.new()
@@ -38,68 +84,227 @@ var ffi = KataruInterface.new()
 # Signals - subscribe to these signals to listen to dialogue events.
 # ------------------------------------------------------------------------------
 
+# Every dialogue signal carries the id of the runner that produced it.
+# Lines from `run`, `next`, etc. come from the runner with id `MAIN_RUNNER`.
+
 # Signals a character saying a line of dialogue.
-signal dialogue(character: String, text: String, attributes: Array[Dictionary])
+# `line_id` is stable across runs, for keying voice-over or analytics to the line, and is its translation key.
+signal dialogue(runner_id: String, character: String, text: String, attributes: Array[Dictionary], line_id: String, passage: String)
 
 # Signals an array of choices that the player can make.
-signal choices(choices: Array[String], timeout: float)
+# Each choice is a dictionary with its `id`, `text`, `target` passage and guarding `condition`,
+# whether it's `available` (false if its condition hid it) and whether it was `visited` before.
+signal choices(runner_id: String, choices: Array[Dictionary], timeout: float)
+
+# Signals that timed choices ran out of time. `choice` is submitted right after.
+signal choice_timeout(runner_id: String, choice: String)
 
 # Signals a command issued by Kataru, which should trigger a function call.
-signal command(cmd_name: String, normalized_name: String, params: Dictionary)
+# `params` has every declared parameter, with omitted ones set to their defaults.
+signal command(runner_id: String, cmd_name: String, normalized_name: String, params: Dictionary)
+
+# Signals a command that has no registered function.
+signal unhandled_command(runner_id: String, cmd_name: String, normalized_name: String, params: Dictionary)
 
 # Signals a command asking for input from the user to be stored in Kataru state.
-signal input_command(input: Dictionary, timeout: float)
+# `input` maps each state variable to its prompt. Answer it with `submit_input`.
+signal input_command(runner_id: String, input: Dictionary, timeout: float)
 
 # Signals that Kataru has loaded. Other autoload scripts can wait for this signal before running.
 signal loaded
 
+# Signals that the story was recompiled after its source files changed.
+# `position_preserved` is false if the main runner had to restart from the fallback passage.
+signal reloaded(changed_files: PackedStringArray, position_preserved: bool)
+
 # Signals that Kataru has reached the end of the current passage.
-signal end
+signal end(runner_id: String)
+
+# Signals that a call into Kataru failed. `context` describes the call that failed.
+signal error(kind: ErrorKind, message: String, context: String)
+
+const MAIN_RUNNER = "main"
+
+# Return this from a command's function to hold the dialogue until `complete_command` is called.
+# Returning a Signal instead holds the dialogue until the signal is emitted.
+const PENDING = "kataru_pending"
 
 
 # Runs the first line in a given passage.
-func run(passage: String):
-	self.ffi.run(passage)
+func run(passage: String) -> ErrorKind:
+	return self.ffi.run(passage)
 
 
 # Runs lines in the given passage until a choice is encountered.
-func run_until_choice(passage: String):
-	self.ffi.run_until_choice(passage)
+func run_until_choice(passage: String) -> ErrorKind:
+	return self.ffi.run_until_choice(passage)
 
 
 # Runs the next line of dialogue in the current passage.
-func next(input: String = ""):
-	self.ffi.next(input)
+func next(input: String = "") -> ErrorKind:
+	return self.ffi.next(input)
+
+
+# Answers an input line with a value for each requested state variable, then runs the next line.
+# Missing, unrequested or mistyped fields are rejected with a validation error.
+func submit_input(inputs: Dictionary, runner_id: String = MAIN_RUNNER) -> ErrorKind:
+	return self.ffi.submit_input_on(runner_id, inputs)
+
+
+# Creates an independent runner sharing the same story, e.g. for ambient NPC chatter.
+# Its bookmark is loaded from `runner_bookmark_path`, or defaulted if empty.
+func create_runner(runner_id: String, runner_bookmark_path: String = "") -> ErrorKind:
+	if runner_bookmark_path != "":
+		runner_bookmark_path = ProjectSettings.globalize_path(runner_bookmark_path)
+	return self.ffi.create_runner(runner_id, runner_bookmark_path)
+
+
+# Removes a runner created with `create_runner`.
+func remove_runner(runner_id: String) -> ErrorKind:
+	return self.ffi.remove_runner(runner_id)
+
+
+# Returns true if a runner with the given id exists.
+func has_runner(runner_id: String) -> bool:
+	return self.ffi.has_runner(runner_id)
+
+
+# Runs the first line in a given passage on the given runner.
+func run_on(runner_id: String, passage: String) -> ErrorKind:
+	return self.ffi.run_on(runner_id, passage)
+
+
+# Runs lines in the given passage on the given runner until a choice is encountered.
+func run_until_choice_on(runner_id: String, passage: String) -> ErrorKind:
+	return self.ffi.run_until_choice_on(runner_id, passage)
+
+
+# Picks the choice at `index` in the `choices` payload.
+func choose(index: int, runner_id: String = MAIN_RUNNER) -> ErrorKind:
+	return self.ffi.choose_on(runner_id, index)
+
 
+# Picks the choice with the given `id` from the `choices` payload.
+func choose_id(choice_id: String, runner_id: String = MAIN_RUNNER) -> ErrorKind:
+	return self.ffi.choose_id_on(runner_id, choice_id)
 
-# Register a function.
+
+# Runs the next line of dialogue on the given runner.
+func next_on(runner_id: String, input: String = "") -> ErrorKind:
+	return self.ffi.next_on(runner_id, input)
+
+
+# Register a function to be called for a command.
 # If registering for a specific character, specify the char_name.
 func register(f: Callable, cmd_name: String, char_name: String = ""):
-	if char_name != "":
-		print("register for character: ", char_name)
-		cmd_name = cmd_name.replace("$character.", char_name + ".")
-	if self.debug_level > DebugLevel.NONE:
-		print("Kataru.register(): Registering ", cmd_name)
-	Commands.registry[cmd_name] = f
+	self.ffi.register_command(cmd_name, f, char_name)
+
+
+# Marks a pending command as complete, letting the runner continue.
+func complete_command(runner_id: String = MAIN_RUNNER) -> ErrorKind:
+	return self.ffi.complete_command(runner_id)
+
+
+# Returns true if the runner is waiting on a pending command.
+func is_waiting(runner_id: String = MAIN_RUNNER) -> bool:
+	return self.ffi.is_waiting(runner_id)
+
+
+# Turns auto-play on or off. Auto-play advances lines on a timer and waits at choices and inputs.
+func set_autoplay(enabled: bool, runner_id: String = MAIN_RUNNER):
+	self.ffi.set_autoplay(runner_id, enabled)
+
+
+# Pauses or resumes auto-play.
+func set_autoplay_paused(paused: bool, runner_id: String = MAIN_RUNNER):
+	self.ffi.set_autoplay_paused(runner_id, paused)
+
+
+# Returns true if auto-play is on and not paused.
+func is_autoplaying(runner_id: String = MAIN_RUNNER) -> bool:
+	return self.ffi.is_autoplaying(runner_id)
+
+
+# Skips the rest of the current auto-played line.
+func skip_line(runner_id: String = MAIN_RUNNER) -> bool:
+	return self.ffi.skip_line(runner_id)
+
+
+# Sets which lines are skipped. Skipping stops at unread lines (unless skipping all),
+# choices, inputs and the end, and carries on once the player moves past them.
+func set_skip_mode(mode: SkipMode, runner_id: String = MAIN_RUNNER) -> ErrorKind:
+	return self.ffi.set_skip_mode(runner_id, mode)
+
+
+func get_skip_mode(runner_id: String = MAIN_RUNNER) -> SkipMode:
+	return self.ffi.get_skip_mode(runner_id)
+
+
+# Sets the choice submitted when the current timed choices run out of time.
+# Without one, the first choice is submitted.
+func set_timeout_choice(choice: String, runner_id: String = MAIN_RUNNER) -> ErrorKind:
+	return self.ffi.set_timeout_choice(runner_id, choice)
+
+
+# Returns the seconds left on the current timed choices, or -1 if there are none.
+func get_choice_time_left(runner_id: String = MAIN_RUNNER) -> float:
+	return self.ffi.get_choice_time_left(runner_id)
+
+
+# Unregister the function for a command.
+func unregister(cmd_name: String, char_name: String = "") -> bool:
+	return self.ffi.unregister_command(cmd_name, char_name)
 
 
 func _connect_callbacks():
 	self.ffi.loaded.connect(func(): self.loaded.emit())
+	self.ffi.reloaded.connect(
+		func(changed_files: PackedStringArray, position_preserved: bool): self.reloaded.emit(
+			changed_files, position_preserved
+		)
+	)
 	self.ffi.fatal.connect(func(message: String): assert(false, message))
 
 	self.ffi.dialogue.connect(
-		func(char_name: String, text: String, attributes: String): self.dialogue.emit(
-			char_name, text, JSON.parse_string(attributes)
+		func(runner_id: String, char_name: String, text: String, attributes: Array, line_id: String, passage: String): self.dialogue.emit(
+			runner_id, char_name, text, attributes, line_id, passage
 		)
 	)
 	self.ffi.choices.connect(
-		func(choice_list: Array[String], timeout: float): self.choices.emit(choice_list, timeout)
+		func(runner_id: String, choice_list: Array[Dictionary], timeout: float): self.choices.emit(
+			runner_id, choice_list, timeout
+		)
+	)
+	self.ffi.choice_timeout.connect(
+		func(runner_id: String, choice: String): self.choice_timeout.emit(runner_id, choice)
+	)
+	self.ffi.command.connect(
+		func(runner_id: String, cmd_name: String, normalized_name: String, params: Dictionary): self.command.emit(
+			runner_id, cmd_name, normalized_name, params
+		)
+	)
+	self.ffi.command_pending.connect(self._on_command_pending)
+	self.ffi.unhandled_command.connect(
+		func(runner_id: String, cmd_name: String, normalized_name: String, params: Dictionary): self.unhandled_command.emit(
+			runner_id, cmd_name, normalized_name, params
+		)
 	)
-	self.ffi.command.connect(self.Commands.call_command)
 	self.ffi.input_command.connect(
-		func(inputs: Dictionary, timeout: float): self.input.emit(inputs, timeout)
+		func(runner_id: String, inputs: Dictionary, timeout: float): self.input_command.emit(
+			runner_id, inputs, timeout
+		)
+	)
+	self.ffi.end.connect(func(runner_id: String): self.end.emit(runner_id))
+	self.ffi.error.connect(
+		func(kind: int, message: String, context: String): self.error.emit(kind, message, context)
 	)
-	self.ffi.end.connect(func(): self.end.emit())
+
+
+# Waits on commands that returned a Signal, then completes them.
+func _on_command_pending(runner_id: String, awaitable):
+	if awaitable is Signal:
+		await awaitable
+		self.ffi.complete_command(runner_id)
 
 
 func init():
@@ -111,11 +316,24 @@ func init():
 		story_src_path = ProjectSettings.globalize_path(self.story_path)
 		codegen_path = ProjectSettings.globalize_path(CODEGEN_PATH)
 
+	self.ffi.set_persist_history(self.persist_history)
+	self.ffi.set_reload_fallback_passage(self.reload_fallback_passage)
+	self.ffi.set_strict_commands(self.strict_commands)
+	self.ffi.set_advance_after_commands(self.advance_after_commands)
+	self.ffi.set_autoplay_timing(self.autoplay_chars_per_second, self.autoplay_min_line_time)
+	if self.strings_path != "":
+		self.ffi.set_localization(ProjectSettings.globalize_path(self.strings_path), self.source_locale)
+	self.ffi.set_locale(self.locale if self.locale != "" else TranslationServer.get_locale())
+	self.ffi.set_read_lines_path(ProjectSettings.globalize_path(self.read_lines_path))
+	self.ffi.set_slots_path(ProjectSettings.globalize_path(self.slots_path))
+	self.ffi.set_autosave(self.autosave_triggers, self.autosave_every_lines, self.autosave_slots)
+	self.ffi.set_migrations_path(ProjectSettings.globalize_path(self.migrations_path))
 	self.ffi.init(
 		story_src_path,
 		ProjectSettings.globalize_path(self.compiled_story_path),
 		ProjectSettings.globalize_path(self.bookmark_path),
 		codegen_path,
+		self.codegen_targets | CodegenTarget.GDSCRIPT,
 		self.default_passage,
 		self.debug_level,
 		self.watch_poll_interval
@@ -128,22 +346,125 @@ func _ready():
 	self.init()
 
 
-func set_state(variable: String, value):
-	self.ffi.set_state(variable, value)
+# Sets a state variable. Ints are stored as numbers; arrays and dictionaries are not supported.
+func set_state(variable: String, value) -> ErrorKind:
+	return self.ffi.set_state(variable, value)
 
 
+# Returns the value of a state variable, or null if it does not exist.
 func get_state(variable: String):
-	self.ffi.get_state(variable)
+	return self.ffi.get_state(variable)
+
+
+# Returns all state variables, keyed by `namespace:name` (or just `name` for global variables).
+func get_all_state() -> Dictionary:
+	return self.ffi.get_all_state()
+
+
+# Translates dialogue and choices to `new_locale` with the translations imported from `strings_path`,
+# falling back to the story's text.
+func set_locale(new_locale: String):
+	self.locale = new_locale
+	self.ffi.set_locale(new_locale)
+
+
+func save(path: String) -> ErrorKind:
+	return self.ffi.save(path)
+
+
+func load(path: String) -> ErrorKind:
+	return self.ffi.load(path)
+
+
+# Saves to the slot named `slot_name`, replacing it. `metadata` is returned by `list_slots`.
+func save_slot(slot_name: String, metadata: Dictionary = {}) -> ErrorKind:
+	return self.ffi.save_slot(slot_name, metadata)
+
+
+# Returns every save slot, most recently saved first.
+# Each has its `name`, `timestamp` (Unix time), `passage`, `playtime` and `metadata`.
+func list_slots() -> Array:
+	return self.ffi.list_slots()
+
 
+func delete_slot(slot_name: String) -> ErrorKind:
+	return self.ffi.delete_slot(slot_name)
 
-func save(path: String):
-	self.ffi.save(path)
 
+func load_slot(slot_name: String) -> ErrorKind:
+	return self.ffi.load_slot(slot_name)
 
-func load(path: String):
-	self.ffi.load(path)
+
+# Returns the main runner's bookmark as bytes, e.g. for uploading to cloud saves.
+func snapshot() -> PackedByteArray:
+	return self.ffi.snapshot()
+
+
+# Returns the main runner's bookmark as a JSON string.
+func snapshot_json() -> String:
+	return self.ffi.snapshot_json()
+
+
+# Loads a bookmark from `snapshot`. Corrupt snapshots are rejected with an error.
+func restore(bytes: PackedByteArray) -> ErrorKind:
+	return self.ffi.restore(bytes)
+
+
+# Loads a bookmark from `snapshot_json`.
+func restore_json(source: String) -> ErrorKind:
+	return self.ffi.restore_json(source)
+
+
+# Sets a function run on saves from older versions of the story, after `migrations_path` renames.
+# It's called with `(bookmark: Dictionary, report: Dictionary)`, where `report` has `missing_passage`
+# and `missing_variables`, and can return a fixed bookmark dictionary, or null to keep it.
+func set_migration_callback(callable: Callable):
+	self.ffi.set_migration_callback(callable)
+
+
+# Changes when the main runner is autosaved, e.g. `set_autosave(AutosaveTrigger.PASSAGE | AutosaveTrigger.CHOICE)`.
+func set_autosave(triggers: int, every_lines: int = 20, slots: int = 3):
+	self.ffi.set_autosave(triggers, every_lines, slots)
+
+
+# Returns the seconds played, including time from the loaded save slot.
+func get_playtime() -> float:
+	return self.ffi.get_playtime()
+
+
+# Returns the last `limit` lines emitted by the main runner, oldest first (all lines if negative).
+# Each entry has `kind`, `passage`, `speaker`, `text`, `attributes`, `choices` and `chosen`.
+func get_history(limit: int = -1) -> Array:
+	return self.ffi.get_history(limit)
+
+
+# Forgets every line emitted by the main runner.
+func clear_history():
+	self.ffi.clear_history()
+
+
+# Goes back `steps` dialogue or choices lines and re-emits that line's signal.
+# Commands in between aren't run again. Only the last 100 lines can be gone back to.
+func rewind(steps: int = 1) -> ErrorKind:
+	return self.ffi.rewind(steps)
+
+
+# Returns the error message from the most recent call, or "" if it succeeded.
+func last_error() -> String:
+	return self.ffi.last_error()
+
+
+# Returns the kind of error from the most recent call, or `ErrorKind.NONE` if it succeeded.
+func last_error_kind() -> ErrorKind:
+	return self.ffi.last_error_kind()
 
 
 # Called every frame. 'delta' is the elapsed time since the previous frame.
 func _process(delta: float):
 	self.ffi.watch_story_dir(delta)
+	self.ffi.tick(delta)
+
+
+# Keep the lines read this session when the game closes.
+func _exit_tree():
+	self.ffi.save_read_lines()
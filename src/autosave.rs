@@ -0,0 +1,98 @@
+//! Autosaving the main runner to a rotating set of save slots.
+use std::path::Path;
+
+use kataru::*;
+
+use crate::slots;
+
+/// Bitflags selecting what sets off an autosave.
+pub type AutosaveTrigger = u8;
+pub const AUTOSAVE_PASSAGE: AutosaveTrigger = 1;
+pub const AUTOSAVE_CHOICE: AutosaveTrigger = 2;
+pub const AUTOSAVE_LINES: AutosaveTrigger = 4;
+
+/// Autosaves go to slots named `autosave-0`, `autosave-1`, ...
+const SLOT_PREFIX: &str = "autosave-";
+
+fn slot_index(name: &str) -> Option<u32> {
+    name.strip_prefix(SLOT_PREFIX)?.parse().ok()
+}
+
+/// Whether the runner moved to another passage between `before` and `after`.
+pub fn changed_passage(before: &Bookmark, after: &Bookmark) -> bool {
+    before.namespace() != after.namespace() || before.passage() != after.passage()
+}
+
+pub struct Autosave {
+    pub triggers: AutosaveTrigger,
+    /// Lines of dialogue between autosaves with `AUTOSAVE_LINES`.
+    pub every_lines: u32,
+    /// How many autosave slots to rotate through.
+    pub slots: u32,
+    /// Set while skipping, so skipped lines aren't saved one by one.
+    /// A save that comes due meanwhile is made once skipping stops.
+    pub suspended: bool,
+    deferred: bool,
+    lines: u32,
+    // Found from the newest existing autosave on the first save.
+    next_slot: Option<u32>,
+}
+
+impl Default for Autosave {
+    fn default() -> Self {
+        Self {
+            triggers: 0,
+            every_lines: 20,
+            slots: 3,
+            suspended: false,
+            deferred: false,
+            lines: 0,
+            next_slot: None,
+        }
+    }
+}
+
+impl Autosave {
+    /// Counts a line from the main runner, returning whether it should be autosaved.
+    /// Only dialogue counts towards `every_lines`.
+    pub fn on_line(&mut self, dialogue: bool, passage_changed: bool, chose: bool) -> bool {
+        if self.triggers == 0 {
+            return false;
+        }
+        if dialogue {
+            self.lines += 1;
+        }
+        let due = (self.triggers & AUTOSAVE_PASSAGE != 0 && passage_changed)
+            || (self.triggers & AUTOSAVE_CHOICE != 0 && chose)
+            || (self.triggers & AUTOSAVE_LINES != 0
+                && self.every_lines > 0
+                && self.lines >= self.every_lines);
+        if due {
+            self.lines = 0;
+        }
+        if due && self.suspended {
+            self.deferred = true;
+            return false;
+        }
+        due
+    }
+
+    /// Stops suspending autosaves, returning whether one came due while they were suspended.
+    pub fn resume(&mut self) -> bool {
+        self.suspended = false;
+        std::mem::take(&mut self.deferred)
+    }
+
+    /// The slot the next autosave goes to, replacing the oldest once every slot is used.
+    pub fn next_slot(&mut self, slots_path: &Path) -> String {
+        let index = *self.next_slot.get_or_insert_with(|| {
+            // Slots are listed newest first, so carry on after the newest autosave.
+            slots::list(slots_path)
+                .ok()
+                .and_then(|slots| slots.iter().find_map(|slot| slot_index(&slot.name)))
+                .map_or(0, |index| index + 1)
+        }) % self.slots.max(1);
+        self.next_slot = Some(index + 1);
+        format!("{}{}", SLOT_PREFIX, index)
+    }
+}
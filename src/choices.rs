@@ -0,0 +1,159 @@
+//! Metadata for the choices a runner offers: where each leads, whether it's been picked before,
+//! and the condition guarding it.
+use std::collections::HashSet;
+
+use godot::prelude::*;
+use kataru::*;
+
+use crate::convert::{edit_state, serialize_state};
+use crate::reload::find_passage;
+use crate::status::*;
+
+#[derive(Clone, Debug)]
+pub struct ChoiceInfo {
+    /// The choice's key in the story, which `choose_id` and `next` expect.
+    pub id: String,
+    pub text: String,
+    /// The passage the choice leads to, or empty if it continues the current passage.
+    pub target: String,
+    /// The condition guarding the choice, or empty if it's always offered.
+    pub condition: String,
+    /// False if the condition hid the choice, so it can only be shown disabled.
+    pub available: bool,
+    pub visited: bool,
+}
+
+impl ChoiceInfo {
+    pub fn to_dictionary(&self) -> Dictionary {
+        let mut dict = Dictionary::new();
+        dict.insert("id", self.id.clone());
+        dict.insert("text", self.text.clone());
+        dict.insert("target", self.target.clone());
+        dict.insert("condition", self.condition.clone());
+        dict.insert("available", self.available);
+        dict.insert("visited", self.visited);
+        dict
+    }
+}
+
+/// Picked choices are kept in the bookmark's state under this namespace,
+/// so they're saved, loaded and restored along with the rest of it.
+pub const VISITED_NAMESPACE: &str = "$visited";
+
+// Choices are keyed by the line offering them, since a passage can offer the same choice twice.
+fn visit_key(at: &Bookmark, choice: &str) -> String {
+    format!(
+        "{}:{}:{}/{}",
+        at.namespace(),
+        at.passage(),
+        at.line(),
+        choice
+    )
+}
+
+/// Returns `bookmark` with `choice`, offered at the position in `at`, marked as picked.
+pub fn mark_visited(bookmark: &Bookmark, at: &Bookmark, choice: &str) -> InterfaceResult<Bookmark> {
+    let key = visit_key(at, choice);
+    edit_state(bookmark, |state| {
+        state
+            .entry(VISITED_NAMESPACE.to_string())
+            .or_default()
+            .insert(key, serde_json::Value::Bool(true));
+    })
+}
+
+// Every choice picked in `bookmark`'s playthrough.
+fn visited_keys(bookmark: &Bookmark) -> HashSet<String> {
+    serialize_state(bookmark)
+        .ok()
+        .and_then(|(_, mut state)| state.remove(VISITED_NAMESPACE))
+        .map(|visited| visited.into_iter().map(|(key, _)| key).collect())
+        .unwrap_or_default()
+}
+
+/// A choice as written in the story: its label, target and guarding condition.
+pub type ChoiceSource = (String, String, String);
+
+/// Flattens a `choices:` block, where conditional choices are grouped under `if <condition>` keys.
+pub fn flatten_choices(block: &serde_json::Map<String, serde_json::Value>) -> Vec<ChoiceSource> {
+    let mut choices = Vec::new();
+    for (key, value) in block {
+        match (key.strip_prefix("if "), value) {
+            (Some(condition), serde_json::Value::Object(group)) => {
+                for (text, target) in group {
+                    choices.push((
+                        text.clone(),
+                        target.as_str().unwrap_or_default().to_string(),
+                        condition.trim().to_string(),
+                    ));
+                }
+            }
+            _ => choices.push((
+                key.clone(),
+                value.as_str().unwrap_or_default().to_string(),
+                String::new(),
+            )),
+        }
+    }
+    choices
+}
+
+// Finds every `choices:` block in a serialized passage, including ones nested in branches.
+fn find_choice_blocks(value: &serde_json::Value, blocks: &mut Vec<Vec<ChoiceSource>>) {
+    match value {
+        serde_json::Value::Object(object) => {
+            if let Some(serde_json::Value::Object(block)) = object.get("choices") {
+                blocks.push(flatten_choices(block));
+            }
+            for child in object.values() {
+                find_choice_blocks(child, blocks);
+            }
+        }
+        serde_json::Value::Array(array) => {
+            for child in array {
+                find_choice_blocks(child, blocks);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Describes the `choices` a runner at `bookmark` is offering.
+/// The block they came from is looked up in the story to find targets and hidden choices;
+/// if it can't be found, only the offered choices are described.
+pub fn describe_choices(story: &Story, bookmark: &Bookmark, choices: &Choices) -> Vec<ChoiceInfo> {
+    let offered: Vec<String> = choices.choices.iter().map(|c| c.to_string()).collect();
+    let mut blocks = Vec::new();
+    if let Some(passage) = find_passage(story, bookmark.namespace(), bookmark.passage()) {
+        if let Ok(passage) = serde_json::to_value(passage) {
+            find_choice_blocks(&passage, &mut blocks);
+        }
+    }
+    let visited = visited_keys(bookmark);
+    // The block offering these choices is the first one containing all of them.
+    let sources = blocks
+        .into_iter()
+        .find(|block| {
+            offered
+                .iter()
+                .all(|text| block.iter().any(|(source, _, _)| source == text))
+        })
+        .unwrap_or_else(|| {
+            offered
+                .iter()
+                .map(|text| (text.clone(), String::new(), String::new()))
+                .collect()
+        });
+
+    sources
+        .into_iter()
+        .map(|(text, target, condition)| ChoiceInfo {
+            available: offered.contains(&text),
+            visited: visited.contains(&visit_key(bookmark, &text)),
+            id: text.clone(),
+            text,
+            target,
+            condition,
+        })
+        .collect()
+}
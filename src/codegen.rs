@@ -1,11 +1,23 @@
 use kataru::*;
 use std::{fs, path::PathBuf};
 
+mod csharp;
+mod gdscript;
+mod rust;
+
+/// Bitflags selecting which languages to generate constants for.
+pub type CodegenTarget = u8;
+pub const CODEGEN_GDSCRIPT: CodegenTarget = 1;
+pub const CODEGEN_CSHARP: CodegenTarget = 2;
+pub const CODEGEN_RUST: CodegenTarget = 4;
+
 struct StoryConsts {
     namespaces: Vec<String>,
     passages: Vec<String>,
     characters: Vec<String>,
     commands: Vec<(String, Option<Params>)>,
+    state: Vec<(String, Value)>,
+    attributes: Vec<String>,
 }
 impl StoryConsts {
     /// Generates the code for
@@ -16,6 +28,8 @@ impl StoryConsts {
         let mut passages = Vec::<String>::with_capacity(story.sections.len());
         let mut characters = Vec::<String>::with_capacity(story.sections.len());
         let mut commands = Vec::<(String, Option<Params>)>::with_capacity(story.sections.len());
+        let mut state = Vec::<(String, Value)>::with_capacity(story.sections.len());
+        let mut attributes = Vec::<String>::with_capacity(story.sections.len());
 
         // Keep track of namespace boundaries for passages and characters.
         // The last passage index used for this namespace`passage_bounds[namespace_enum]`.
@@ -33,6 +47,12 @@ impl StoryConsts {
                 for (command_name, params) in &section.config.commands {
                     commands.push((command_name.to_string(), params.clone()));
                 }
+                for (variable, value) in &section.config.state {
+                    state.push((variable.to_string(), value.clone()));
+                }
+                for (attribute, _attribute_data) in &section.config.attributes {
+                    attributes.push(attribute.to_string());
+                }
             } else {
                 namespaces.push(namespace.to_string());
                 for (character, _character_data) in &section.config.characters {
@@ -44,6 +64,12 @@ impl StoryConsts {
                 for (command_name, params) in &section.config.commands {
                     commands.push((format!("{}:{}", namespace, command_name), params.clone()));
                 }
+                for (variable, value) in &section.config.state {
+                    state.push((format!("{}:{}", namespace, variable), value.clone()));
+                }
+                for (attribute, _attribute_data) in &section.config.attributes {
+                    attributes.push(format!("{}:{}", namespace, attribute));
+                }
             }
         }
 
@@ -51,137 +77,103 @@ impl StoryConsts {
         passages.sort();
         characters.sort();
         commands.sort_by(|(name1, _params1), (name2, _params2)| name1.cmp(name2));
+        state.sort_by(|(name1, _value1), (name2, _value2)| name1.cmp(name2));
+        attributes.sort();
         Self {
             namespaces,
             passages,
             characters,
             commands,
+            state,
+            attributes,
         }
     }
 }
 
-/// Using the already loaded story, generate constants;
-pub fn try_codegen_consts(path: &PathBuf, story: &Story) -> Result<()> {
+/// A codegen backend, producing source files from the story's constants.
+trait Backend {
+    /// Returns the name and source of each file to write to the codegen path.
+    fn generate(&self, consts: &StoryConsts) -> Vec<(&'static str, String)>;
+}
+
+/// Using the already loaded story, generate constants for each of the given `targets`.
+pub fn try_codegen_consts(path: &PathBuf, story: &Story, targets: CodegenTarget) -> Result<()> {
     let consts = StoryConsts::new(story);
-    write_consts_file(
-        &path.join("namespaces.gd"),
-        fill_consts_template(consts.namespaces),
-    )?;
-    write_consts_file(
-        &path.join("characters.gd"),
-        fill_consts_template(consts.characters),
-    )?;
-    write_consts_file(
-        &path.join("passages.gd"),
-        fill_consts_template(consts.passages),
-    )?;
-    write_consts_file(
-        &path.join("commands.gd"),
-        fill_cmd_consts_template(consts.commands),
-    )?;
+    let backends: [(CodegenTarget, &dyn Backend); 3] = [
+        (CODEGEN_GDSCRIPT, &gdscript::GDScript),
+        (CODEGEN_CSHARP, &csharp::CSharp),
+        (CODEGEN_RUST, &rust::Rust),
+    ];
+    for (target, backend) in backends {
+        if targets & target == 0 {
+            continue;
+        }
+        for (file_name, source) in backend.generate(&consts) {
+            write_consts_file(&path.join(file_name), source)?;
+        }
+    }
     Ok(())
 }
 
+// GDScript keywords that can't be used as constant names.
+const GD_KEYWORDS: &[&str] = &[
+    "and",
+    "as",
+    "assert",
+    "await",
+    "break",
+    "breakpoint",
+    "class",
+    "class_name",
+    "const",
+    "continue",
+    "elif",
+    "else",
+    "enum",
+    "extends",
+    "for",
+    "func",
+    "if",
+    "in",
+    "is",
+    "match",
+    "namespace",
+    "not",
+    "or",
+    "pass",
+    "preload",
+    "return",
+    "self",
+    "signal",
+    "static",
+    "super",
+    "trait",
+    "var",
+    "void",
+    "when",
+    "while",
+    "yield",
+];
+
 /// Convert a kataru identifier to a Godot varname.
+/// Every backend uses these names, so constants match across languages.
 fn get_varname(name: &str) -> String {
     if name == "global" {
         "Global".to_string()
     } else {
-        name.replace(r"$character.", "character_").replace(":", "_")
+        let varname = name
+            .replace(r"$character.", "character_")
+            .replace(r"$passage.", "passage_")
+            .replace(":", "_");
+        // Keywords such as `var` get a trailing underscore.
+        if GD_KEYWORDS.contains(&varname.as_str()) {
+            format!("{}_", varname)
+        } else {
+            varname
+        }
     }
 }
 
-// Write a definition statement for the given value.
-fn format_vardef(value: &str) -> String {
-    format!("const {} = \"{}\"", get_varname(value.as_ref()), value)
-}
-
-fn format_params(param: &str) -> String {
-    format!("params.{param}")
-}
-
-fn format_adapter(value: &str, params: &Option<Params>) -> String {
-    let param_strings: Vec<String> = match params {
-        Some(params) => params
-            .iter()
-            .map(|(param, _value)| format_params(param))
-            .collect(),
-        None => Vec::new(),
-    };
-    format!(
-        "{funcname}: func(f: Callable, params: Dictionary): f.call({params})",
-        funcname = get_varname(value),
-        params = param_strings.join(",")
-    )
-}
-
-fn fill_consts_template(values: Vec<String>) -> String {
-    let def_separator = "\n";
-    let list_separator = ",\n    ";
-    let csv_separator = ",";
-
-    let defs: Vec<String> = values.iter().map(|value| format_vardef(value)).collect();
-    let vars: Vec<String> = values.iter().map(|value| get_varname(value)).collect();
-    format!(
-        r###"# DO NOT EDIT.
-# This file was autogenerated by Kataru based on your story.
-{defs}
-
-const NAMES: Array[String] = [
-    {vars},
-]
-
-
-# Returns the property for usage in the editor.
-static func property(property_name: String) -> Dictionary:
-	return {{
-		"name": property_name,
-		"type": TYPE_STRING,
-		"usage": PROPERTY_USAGE_DEFAULT,
-		"hint": PROPERTY_HINT_ENUM,
-		"hint_string": "{csv}"
-	}}
-"###,
-        defs = defs.join(def_separator),
-        vars = vars.join(list_separator),
-        csv = values.join(csv_separator)
-    )
-}
-
-fn fill_cmd_consts_template(values: Vec<(String, Option<Params>)>) -> String {
-    let def_separator = "\n";
-    let list_separator = ",\n    ";
-
-    let defs: Vec<String> = values
-        .iter()
-        .map(|(value, _params)| format_vardef(value))
-        .collect();
-    let adapters: Vec<String> = values
-        .iter()
-        .map(|(value, params)| format_adapter(value, params))
-        .collect();
-    format!(
-        r###"# DO NOT EDIT.
-# This file was autogenerated by Kataru based on your story.
-{defs}
-
-# Adapters.
-var adapters: Dictionary = {{
-    {adapters}
-}}
-
-var registry: Dictionary = {{}}
-
-
-func call_command(cmd_name: String, normalized_name: String, params: String):
-	self.adapters[normalized_name].call(self.registry[cmd_name], JSON.parse_string(params))
-
-"###,
-        defs = defs.join(def_separator),
-        adapters = adapters.join(list_separator)
-    )
-}
-
 fn write_consts_file(path: &PathBuf, source: String) -> Result<()> {
     match fs::read_to_string(path) {
         Ok(old_source) => {
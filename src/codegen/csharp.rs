@@ -0,0 +1,249 @@
+//! C# constants and command dispatch, for gameplay code written on Godot .NET.
+use kataru::*;
+
+use super::{get_varname, Backend, StoryConsts};
+
+// C# keywords, which must be escaped with `@` to be used as names.
+const CS_KEYWORDS: &[&str] = &[
+    "abstract",
+    "as",
+    "base",
+    "bool",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "char",
+    "checked",
+    "class",
+    "const",
+    "continue",
+    "decimal",
+    "default",
+    "delegate",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "event",
+    "explicit",
+    "extern",
+    "false",
+    "finally",
+    "fixed",
+    "float",
+    "for",
+    "foreach",
+    "goto",
+    "if",
+    "implicit",
+    "in",
+    "int",
+    "interface",
+    "internal",
+    "is",
+    "lock",
+    "long",
+    "namespace",
+    "new",
+    "null",
+    "object",
+    "operator",
+    "out",
+    "override",
+    "params",
+    "private",
+    "protected",
+    "public",
+    "readonly",
+    "ref",
+    "return",
+    "sbyte",
+    "sealed",
+    "short",
+    "sizeof",
+    "stackalloc",
+    "static",
+    "string",
+    "struct",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "uint",
+    "ulong",
+    "unchecked",
+    "unsafe",
+    "ushort",
+    "using",
+    "virtual",
+    "void",
+    "volatile",
+    "while",
+];
+
+pub struct CSharp;
+
+impl Backend for CSharp {
+    fn generate(&self, consts: &StoryConsts) -> Vec<(&'static str, String)> {
+        let classes = [
+            format_class("Namespaces", &consts.namespaces),
+            format_class("Passages", &consts.passages),
+            format_class("Characters", &consts.characters),
+            format_class(
+                "Commands",
+                &consts
+                    .commands
+                    .iter()
+                    .map(|(name, _params)| name.clone())
+                    .collect::<Vec<String>>(),
+            ),
+        ];
+        let source = format!(
+            r###"// DO NOT EDIT.
+// This file was autogenerated by Kataru based on your story.
+using Godot.Collections;
+
+namespace Kataru
+{{
+{classes}
+
+    /// <summary>Implement to handle the commands declared in the story.</summary>
+    public interface ICommandHandler
+    {{
+{methods}
+    }}
+
+    public static class CommandDispatcher
+    {{
+        /// <summary>
+        /// Calls the handler method for a command, filling missing parameters from the declared defaults.
+        /// Returns false if the command is unknown.
+        /// </summary>
+        public static bool Dispatch(ICommandHandler handler, string cmdName, string normalizedName, Dictionary parameters)
+        {{
+            switch (normalizedName)
+            {{
+{cases}
+                default:
+                    return false;
+            }}
+        }}
+
+        // The character a `$character` command was issued for.
+        private static string Character(string cmdName) => cmdName.Substring(0, cmdName.LastIndexOf('.'));
+    }}
+}}
+"###,
+            classes = classes.join("\n\n"),
+            methods = consts
+                .commands
+                .iter()
+                .map(|(name, params)| format_method(name, params))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            cases = consts
+                .commands
+                .iter()
+                .map(|(name, params)| format_case(name, params))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        );
+        vec![("KataruConsts.cs", source)]
+    }
+}
+
+// The C# name for a kataru identifier, escaping keywords.
+fn cs_name(name: &str) -> String {
+    let varname = get_varname(name);
+    if CS_KEYWORDS.contains(&varname.as_str()) {
+        format!("@{}", varname)
+    } else {
+        varname
+    }
+}
+
+fn cs_type(value: &Value) -> &'static str {
+    match value {
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "double",
+        Value::String(_) => "string",
+    }
+}
+
+fn format_literal(value: &Value) -> String {
+    match value {
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) if n.fract() == 0.0 => format!("{:.1}", n),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => serde_json::to_string(s).unwrap_or_else(|_| "\"\"".to_string()),
+    }
+}
+
+// Reads a parameter out of the command's dictionary, or its declared default.
+fn format_param_read(param: &str, value: &Value) -> String {
+    let conversion = match value {
+        Value::Bool(_) => "AsBool",
+        Value::Number(_) => "AsDouble",
+        Value::String(_) => "AsString",
+    };
+    format!(
+        "parameters.ContainsKey(\"{param}\") ? parameters[\"{param}\"].{conversion}() : {default}",
+        default = format_literal(value)
+    )
+}
+
+fn is_character_command(name: &str) -> bool {
+    name.contains("$character.")
+}
+
+fn format_class(class_name: &str, values: &[String]) -> String {
+    let defs: Vec<String> = values
+        .iter()
+        .map(|value| {
+            format!(
+                "        public const string {} = \"{}\";",
+                cs_name(value),
+                value
+            )
+        })
+        .collect();
+    let names: Vec<String> = values.iter().map(|value| cs_name(value)).collect();
+    format!(
+        "    public static class {class_name}\n    {{\n{defs}\n\n        public static readonly string[] Names = {{ {names} }};\n    }}",
+        defs = defs.join("\n"),
+        names = names.join(", ")
+    )
+}
+
+fn format_method(name: &str, params: &Option<Params>) -> String {
+    let mut args: Vec<String> = Vec::new();
+    if is_character_command(name) {
+        args.push("string character".to_string());
+    }
+    if let Some(params) = params {
+        for (param, value) in params {
+            args.push(format!("{} {}", cs_type(value), cs_name(param)));
+        }
+    }
+    format!("        void {}({});", cs_name(name), args.join(", "))
+}
+
+fn format_case(name: &str, params: &Option<Params>) -> String {
+    let mut args: Vec<String> = Vec::new();
+    if is_character_command(name) {
+        args.push("Character(cmdName)".to_string());
+    }
+    if let Some(params) = params {
+        for (param, value) in params {
+            args.push(format_param_read(param, value));
+        }
+    }
+    format!(
+        "                case Commands.{varname}:\n                    handler.{varname}({args});\n                    return true;",
+        varname = cs_name(name),
+        args = args.join(", ")
+    )
+}
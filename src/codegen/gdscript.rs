@@ -0,0 +1,186 @@
+//! GDScript constants, loaded by `kataru.gd`.
+use kataru::*;
+
+use super::{get_varname, Backend, StoryConsts};
+
+pub struct GDScript;
+
+impl Backend for GDScript {
+    fn generate(&self, consts: &StoryConsts) -> Vec<(&'static str, String)> {
+        vec![
+            ("namespaces.gd", fill_consts_template(&consts.namespaces)),
+            ("characters.gd", fill_consts_template(&consts.characters)),
+            ("passages.gd", fill_consts_template(&consts.passages)),
+            ("commands.gd", fill_cmd_consts_template(&consts.commands)),
+            ("state.gd", fill_state_consts_template(&consts.state)),
+            (
+                "attributes.gd",
+                fill_attribute_consts_template(&consts.attributes),
+            ),
+        ]
+    }
+}
+
+// Write a definition statement for the given value.
+fn format_vardef(value: &str) -> String {
+    format!("const {} = \"{}\"", get_varname(value.as_ref()), value)
+}
+
+// GDScript type of a parameter, inferred from its default value.
+fn gd_type(value: &Value) -> &'static str {
+    match value {
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "float",
+        Value::String(_) => "String",
+    }
+}
+
+// The `TYPE_*` constant for a parameter, inferred from its default value.
+fn gd_type_const(value: &Value) -> &'static str {
+    match value {
+        Value::Bool(_) => "TYPE_BOOL",
+        Value::Number(_) => "TYPE_FLOAT",
+        Value::String(_) => "TYPE_STRING",
+    }
+}
+
+// Format a value as a GDScript literal.
+fn format_literal(value: &Value) -> String {
+    match value {
+        Value::Bool(b) => b.to_string(),
+        // Keep whole numbers as float literals so they match the inferred type.
+        Value::Number(n) if n.fract() == 0.0 => format!("{:.1}", n),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => serde_json::to_string(s).unwrap_or_else(|_| "\"\"".to_string()),
+    }
+}
+
+// Format the typed signature a registered function should have, e.g. `func(duration: float = 0.3)`.
+fn format_signature(params: &Option<Params>) -> String {
+    let param_strings: Vec<String> = match params {
+        Some(params) => params
+            .iter()
+            .map(|(param, value)| {
+                format!("{param}: {} = {}", gd_type(value), format_literal(value))
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    format!("func({})", param_strings.join(", "))
+}
+
+// Format `entries` one per line between the `open` and `close` brackets.
+// An empty collection is written as just the brackets, since GDScript rejects a lone comma.
+fn format_collection(open: &str, entries: &[String], close: &str) -> String {
+    if entries.is_empty() {
+        return format!("{open}{close}");
+    }
+    format!("{open}\n    {},\n{close}", entries.join(",\n    "))
+}
+
+fn fill_consts_template(values: &[String]) -> String {
+    let def_separator = "\n";
+    let csv_separator = ",";
+
+    let defs: Vec<String> = values.iter().map(|value| format_vardef(value)).collect();
+    let vars: Vec<String> = values.iter().map(|value| get_varname(value)).collect();
+    format!(
+        r###"# DO NOT EDIT.
+# This file was autogenerated by Kataru based on your story.
+{defs}
+
+const NAMES: Array[String] = {vars}
+
+
+# Returns the property for usage in the editor.
+static func property(property_name: String) -> Dictionary:
+	return {{
+		"name": property_name,
+		"type": TYPE_STRING,
+		"usage": PROPERTY_USAGE_DEFAULT,
+		"hint": PROPERTY_HINT_ENUM,
+		"hint_string": "{csv}"
+	}}
+"###,
+        defs = defs.join(def_separator),
+        vars = format_collection("[", &vars, "]"),
+        csv = values.join(csv_separator)
+    )
+}
+
+fn fill_state_consts_template(values: &[(String, Value)]) -> String {
+    let defaults: Vec<String> = values
+        .iter()
+        .map(|(name, value)| format!("{}: {}", get_varname(name), format_literal(value)))
+        .collect();
+    let types: Vec<String> = values
+        .iter()
+        .map(|(name, value)| format!("{}: {}", get_varname(name), gd_type_const(value)))
+        .collect();
+    let names: Vec<String> = values.iter().map(|(name, _value)| name.clone()).collect();
+    format!(
+        r###"{consts}
+
+# Declared default value of each variable.
+const DEFAULTS: Dictionary = {defaults}
+
+# Type of each variable, inferred from its default value.
+const TYPES: Dictionary = {types}
+"###,
+        consts = fill_consts_template(&names),
+        defaults = format_collection("{", &defaults, "}"),
+        types = format_collection("{", &types, "}")
+    )
+}
+
+// The BBCode tag a RichTextLabel uses for an attribute.
+// Unknown attributes keep their name, so they can be handled by a custom `RichTextEffect`.
+fn bbcode_tag(attribute: &str) -> &str {
+    // Namespaced attributes are tagged by their own name.
+    let name = attribute.rsplit(':').next().unwrap_or(attribute);
+    match name {
+        "b" | "bold" | "strong" => "b",
+        "i" | "italic" | "em" => "i",
+        "u" | "underline" => "u",
+        "s" | "strike" | "del" => "s",
+        "code" | "mono" => "code",
+        _ => name,
+    }
+}
+
+fn fill_attribute_consts_template(values: &[String]) -> String {
+    let bbcode: Vec<String> = values
+        .iter()
+        .map(|name| {
+            let tag = bbcode_tag(name);
+            format!("{}: [\"[{tag}]\", \"[/{tag}]\"]", get_varname(name))
+        })
+        .collect();
+    format!(
+        r###"{consts}
+
+# Opening and closing BBCode tags for each attribute, for use in a RichTextLabel.
+const BBCODE: Dictionary = {bbcode}
+"###,
+        consts = fill_consts_template(values),
+        bbcode = format_collection("{", &bbcode, "}")
+    )
+}
+
+fn fill_cmd_consts_template(values: &[(String, Option<Params>)]) -> String {
+    let def_separator = "\n";
+
+    let defs: Vec<String> = values
+        .iter()
+        .map(|(value, params)| format!("# {}\n{}", format_signature(params), format_vardef(value)))
+        .collect();
+    format!(
+        r###"# DO NOT EDIT.
+# This file was autogenerated by Kataru based on your story.
+# Each command is preceded by the signature its registered function should have.
+# Missing parameters are filled in from their defaults and checked before the command is emitted.
+{defs}
+"###,
+        defs = defs.join(def_separator),
+    )
+}
@@ -0,0 +1,168 @@
+//! A Rust module with constants and command dispatch, for tools written in Rust.
+//! Include it with `include!("path/to/kataru_consts.rs");`.
+use kataru::*;
+
+use super::{get_varname, Backend, StoryConsts};
+
+// Rust keywords, which must be raw identifiers to be used as names.
+const RS_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern", "false",
+    "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where", "while",
+    "abstract", "become", "box", "do", "final", "macro", "override", "priv", "try", "typeof",
+    "unsized", "virtual", "yield",
+];
+
+// Keywords that can't be raw identifiers either.
+const RS_RESERVED: &[&str] = &["crate", "self", "super", "Self"];
+
+pub struct Rust;
+
+impl Backend for Rust {
+    fn generate(&self, consts: &StoryConsts) -> Vec<(&'static str, String)> {
+        let modules = [
+            format_module("namespaces", &consts.namespaces),
+            format_module("passages", &consts.passages),
+            format_module("characters", &consts.characters),
+            format_module(
+                "commands",
+                &consts
+                    .commands
+                    .iter()
+                    .map(|(name, _params)| name.clone())
+                    .collect::<Vec<String>>(),
+            ),
+        ];
+        let source = format!(
+            r###"// DO NOT EDIT.
+// This file was autogenerated by Kataru based on your story.
+// Include it with `include!("path/to/kataru_consts.rs");`.
+
+{modules}
+
+/// Implement to handle the commands declared in the story.
+#[allow(non_snake_case)]
+pub trait CommandHandler {{
+{methods}
+}}
+
+/// Calls the handler method for a command, filling missing parameters from the declared defaults.
+/// Returns false if the command is unknown.
+#[allow(unused_variables)]
+pub fn dispatch<H: CommandHandler>(
+    handler: &mut H,
+    cmd_name: &str,
+    normalized_name: &str,
+    params: &kataru::Params,
+) -> bool {{
+    // The character a `$character` command was issued for.
+    let character = cmd_name.rsplit_once('.').map_or("", |(character, _name)| character);
+    match normalized_name {{
+{arms}
+        _ => false,
+    }}
+}}
+"###,
+            modules = modules.join("\n\n"),
+            methods = consts
+                .commands
+                .iter()
+                .map(|(name, params)| format_method(name, params))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            arms = consts
+                .commands
+                .iter()
+                .map(|(name, params)| format_arm(name, params))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        );
+        vec![("kataru_consts.rs", source)]
+    }
+}
+
+// The Rust name for a kataru identifier, escaping keywords.
+fn rs_name(name: &str) -> String {
+    let varname = get_varname(name);
+    if RS_RESERVED.contains(&varname.as_str()) {
+        format!("{}_", varname)
+    } else if RS_KEYWORDS.contains(&varname.as_str()) {
+        format!("r#{}", varname)
+    } else {
+        varname
+    }
+}
+
+fn rs_type(value: &Value) -> &'static str {
+    match value {
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "f64",
+        Value::String(_) => "String",
+    }
+}
+
+// Reads a parameter out of the command's params, or its declared default.
+fn format_param_read(param: &str, value: &Value) -> String {
+    let (variant, read, default) = match value {
+        Value::Bool(b) => ("Bool", "*value", b.to_string()),
+        Value::Number(n) => ("Number", "*value", format!("{:?}", n)),
+        Value::String(s) => (
+            "String",
+            "value.clone()",
+            format!(
+                "{}.to_string()",
+                serde_json::to_string(s).unwrap_or_default()
+            ),
+        ),
+    };
+    format!(
+        "match params.get(\"{param}\") {{ Some(kataru::Value::{variant}(value)) => {read}, _ => {default} }}"
+    )
+}
+
+fn is_character_command(name: &str) -> bool {
+    name.contains("$character.")
+}
+
+fn format_module(module_name: &str, values: &[String]) -> String {
+    let defs: Vec<String> = values
+        .iter()
+        .map(|value| format!("    pub const {}: &str = \"{}\";", rs_name(value), value))
+        .collect();
+    let names: Vec<String> = values.iter().map(|value| rs_name(value)).collect();
+    format!(
+        "#[allow(non_upper_case_globals, dead_code)]\npub mod {module_name} {{\n{defs}\n\n    pub const NAMES: &[&str] = &[{names}];\n}}",
+        defs = defs.join("\n"),
+        names = names.join(", ")
+    )
+}
+
+fn format_method(name: &str, params: &Option<Params>) -> String {
+    let mut args: Vec<String> = vec!["&mut self".to_string()];
+    if is_character_command(name) {
+        args.push("character: &str".to_string());
+    }
+    if let Some(params) = params {
+        for (param, value) in params {
+            args.push(format!("{}: {}", rs_name(param), rs_type(value)));
+        }
+    }
+    format!("    fn {}({});", rs_name(name), args.join(", "))
+}
+
+fn format_arm(name: &str, params: &Option<Params>) -> String {
+    let mut args: Vec<String> = Vec::new();
+    if is_character_command(name) {
+        args.push("character".to_string());
+    }
+    if let Some(params) = params {
+        for (param, value) in params {
+            args.push(format_param_read(param, value));
+        }
+    }
+    format!(
+        "        commands::{varname} => {{\n            handler.{varname}({args});\n            true\n        }}",
+        varname = rs_name(name),
+        args = args.join(", ")
+    )
+}
@@ -0,0 +1,138 @@
+//! Registry of GDScript callables that handle story commands.
+use std::collections::HashMap;
+
+use godot::prelude::*;
+use kataru::*;
+
+use crate::convert::{val_to_variant, value_type_name};
+use crate::status::*;
+
+/// Returned by a command's callable to hold the dialogue until `complete_command` is called.
+pub const PENDING_COMMAND: &str = "kataru_pending";
+
+/// Returns true if a command's return value means it hasn't finished yet:
+/// either the `PENDING_COMMAND` marker or a signal to wait for.
+pub fn is_pending(result: &Variant) -> bool {
+    match result.get_type() {
+        VariantType::Signal => true,
+        VariantType::String | VariantType::StringName => result.to::<String>() == PENDING_COMMAND,
+        _ => false,
+    }
+}
+
+/// Commands a runner is waiting on before it continues.
+#[derive(Default)]
+pub struct PendingCommands {
+    pub count: usize,
+    /// Input from a call to `next` made while waiting, to run once the commands complete.
+    pub deferred_input: Option<String>,
+}
+
+#[derive(Default)]
+pub struct CommandRegistry {
+    // Callables by command name, with `$character` replaced for character commands.
+    callables: HashMap<String, Callable>,
+    // Declared parameters and their defaults, by normalized command name.
+    declared: HashMap<String, Params>,
+}
+
+/// The key a command is registered under.
+/// Character commands registered for `character` have `$character` replaced by its name.
+fn registry_key(name: &str, character: &str) -> String {
+    if character.is_empty() {
+        name.to_string()
+    } else {
+        name.replace("$character.", &format!("{}.", character))
+    }
+}
+
+impl CommandRegistry {
+    /// Records the commands declared in `story`, replacing any previously declared ones.
+    pub fn declare(&mut self, story: &Story) {
+        self.declared.clear();
+        for (namespace, section) in &story.sections {
+            for (command_name, params) in &section.config.commands {
+                let name = if namespace == kataru::GLOBAL {
+                    command_name.to_string()
+                } else {
+                    format!("{}:{}", namespace, command_name)
+                };
+                self.declared
+                    .insert(name, params.clone().unwrap_or_default());
+            }
+        }
+    }
+
+    pub fn register(&mut self, name: &str, callable: Callable, character: &str) {
+        self.callables
+            .insert(registry_key(name, character), callable);
+    }
+
+    pub fn unregister(&mut self, name: &str, character: &str) -> bool {
+        self.callables
+            .remove(&registry_key(name, character))
+            .is_some()
+    }
+
+    /// Returns `params` with the parameters missing from them filled in from the defaults declared
+    /// for `normalized_name`, in declared order, after checking the given ones have the declared types.
+    /// Parameters of undeclared commands are returned as they are.
+    pub fn resolve(&self, normalized_name: &str, params: &Params) -> InterfaceResult<Params> {
+        let Some(declared) = self.declared.get(normalized_name) else {
+            return Ok(params.clone());
+        };
+        let mut resolved = Params::new();
+        for (param, default) in declared {
+            let value = match params.get(param) {
+                Some(value) if value_type_name(value) != value_type_name(default) => {
+                    return Err(KataruError::new(
+                        ERROR_VALIDATION,
+                        format!(
+                            "Command '{}' parameter '{}' should be a {} but was a {}.",
+                            normalized_name,
+                            param,
+                            value_type_name(default),
+                            value_type_name(value)
+                        ),
+                    ))
+                }
+                Some(value) => value,
+                None => default,
+            };
+            resolved.insert(param.clone(), value.clone());
+        }
+        Ok(resolved)
+    }
+
+    /// Calls the callable registered for `cmd_name`, or for `normalized_name` if there isn't one,
+    /// with `params` as resolved by `resolve`.
+    /// Returns what the callable returned, or `None` if no callable is registered for the command.
+    pub fn dispatch(
+        &self,
+        cmd_name: &str,
+        normalized_name: &str,
+        params: &Params,
+    ) -> InterfaceResult<Option<Variant>> {
+        let Some(callable) = self
+            .callables
+            .get(cmd_name)
+            .or_else(|| self.callables.get(normalized_name))
+        else {
+            return Ok(None);
+        };
+        if !self.declared.contains_key(normalized_name) {
+            return Err(KataruError::new(
+                ERROR_VALIDATION,
+                format!(
+                    "Command '{}' is not declared in the story.",
+                    normalized_name
+                ),
+            ));
+        }
+        let mut args = VariantArray::new();
+        for value in params.values() {
+            args.push(val_to_variant(value));
+        }
+        Ok(Some(callable.callv(args)))
+    }
+}
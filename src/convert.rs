@@ -0,0 +1,214 @@
+//! Conversions between Godot `Variant`s and Kataru `Value`s.
+//!
+//! Kataru values are scalars: bools, numbers (`f64`) and strings.
+//! Godot ints are coerced to numbers and string-like variants to strings.
+//! Nested types (arrays, dictionaries, objects, ...) have no Kataru equivalent and are rejected.
+use std::collections::BTreeMap;
+
+use godot::prelude::*;
+use kataru::*;
+
+use crate::choices::VISITED_NAMESPACE;
+use crate::status::*;
+
+/// Converts a Godot variant into a Kataru value.
+pub fn variant_to_val(variant: &Variant) -> Result<Value> {
+    Ok(match variant.get_type() {
+        VariantType::Bool => Value::Bool(variant.to::<bool>()),
+        VariantType::Int => Value::Number(variant.to::<i64>() as f64),
+        VariantType::Float => Value::Number(variant.to::<f64>()),
+        VariantType::String | VariantType::StringName | VariantType::NodePath => {
+            Value::String(variant.to::<String>())
+        }
+        VariantType::Nil => return Err(error!("Null cannot be stored as a Kataru value.")),
+        VariantType::Array | VariantType::Dictionary => {
+            return Err(error!(
+                "Nested type {:?} is not supported as a Kataru value: {}",
+                variant.get_type(),
+                variant
+            ))
+        }
+        _ => {
+            return Err(error!(
+                "Variant of type {:?} could not be converted to a Kataru value: {}",
+                variant.get_type(),
+                variant
+            ))
+        }
+    })
+}
+
+/// Converts a Kataru value into a Godot variant.
+pub fn val_to_variant(value: &Value) -> Variant {
+    match value {
+        Value::Bool(b) => Variant::from(*b),
+        Value::Number(f) => Variant::from(*f),
+        Value::String(s) => Variant::from(s.clone()),
+    }
+}
+
+/// Returns the name of a value's type, for error messages.
+pub fn value_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+    }
+}
+
+/// Converts command parameters into a dictionary of typed values.
+pub fn params_to_dict(params: &Params) -> Dictionary {
+    let mut dict = Dictionary::new();
+    for (param, value) in params {
+        dict.insert(param.clone(), val_to_variant(value));
+    }
+    dict
+}
+
+/// Converts dialogue attributes into an array of dictionaries, each with the `start` and `end`
+/// of the span it applies to and its `params`. Attributes without a parameter map to null.
+pub fn attributes_to_array(attributes: &[AttributedSpan]) -> Array<Dictionary> {
+    Array::from_iter(attributes.iter().map(|span| {
+        let mut params = Dictionary::new();
+        for (attribute, value) in &span.params {
+            params.insert(
+                attribute.clone(),
+                value.as_ref().map_or_else(Variant::nil, val_to_variant),
+            );
+        }
+        let mut dict = Dictionary::new();
+        dict.insert("start", span.start as i64);
+        dict.insert("end", span.end as i64);
+        dict.insert("params", params);
+        dict
+    }))
+}
+
+/// Converts any serializable Kataru data (such as a bookmark) into a Godot variant,
+/// keeping numbers and bools typed.
+pub fn serde_to_variant<T: serde::Serialize>(value: &T) -> Variant {
+    match serde_json::to_value(value) {
+        Ok(value) => json_to_variant(&value),
+        Err(_) => Variant::nil(),
+    }
+}
+
+/// Converts serialized Kataru data into a Godot variant.
+/// Objects become dictionaries and sequences become arrays.
+/// Integers stay ints, other numbers become floats.
+pub fn json_to_variant(value: &serde_json::Value) -> Variant {
+    match value {
+        serde_json::Value::Null => Variant::nil(),
+        serde_json::Value::Bool(b) => Variant::from(*b),
+        serde_json::Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => Variant::from(i),
+            // Godot ints are signed, so anything past `i64::MAX` has to be a float.
+            (None, Some(u)) => Variant::from(u as f64),
+            (None, None) => Variant::from(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => Variant::from(s.clone()),
+        serde_json::Value::Array(values) => Variant::from(Array::<Variant>::from_iter(
+            values.iter().map(json_to_variant),
+        )),
+        serde_json::Value::Object(map) => {
+            let mut dict = Dictionary::new();
+            for (key, value) in map {
+                dict.insert(key.clone(), json_to_variant(value));
+            }
+            Variant::from(dict)
+        }
+    }
+}
+
+/// Converts a Godot variant into JSON, for storing game data such as save slot metadata.
+/// Types without a JSON equivalent are stored as strings.
+pub fn variant_to_json(variant: &Variant) -> serde_json::Value {
+    match variant.get_type() {
+        VariantType::Nil => serde_json::Value::Null,
+        VariantType::Bool => serde_json::Value::Bool(variant.to::<bool>()),
+        VariantType::Int => serde_json::Value::from(variant.to::<i64>()),
+        VariantType::Float => serde_json::Value::from(variant.to::<f64>()),
+        VariantType::String | VariantType::StringName | VariantType::NodePath => {
+            serde_json::Value::String(variant.to::<String>())
+        }
+        VariantType::Array => serde_json::Value::Array(
+            variant
+                .to::<VariantArray>()
+                .iter_shared()
+                .map(|value| variant_to_json(&value))
+                .collect(),
+        ),
+        VariantType::Dictionary => serde_json::Value::Object(
+            variant
+                .to::<Dictionary>()
+                .iter_shared()
+                .map(|(key, value)| (key.to_string(), variant_to_json(&value)))
+                .collect(),
+        ),
+        _ => serde_json::Value::String(variant.to_string()),
+    }
+}
+
+/// Returns every state variable in the bookmark as a flat dictionary.
+/// Global variables are keyed by name, others by `namespace:name` like passages.
+pub fn bookmark_state_to_dict(bookmark: &Bookmark) -> Result<Dictionary> {
+    let serialized = match serde_json::to_value(bookmark) {
+        Ok(serialized) => serialized,
+        Err(err) => return Err(error!("Could not serialize bookmark: {}", err)),
+    };
+    let mut dict = Dictionary::new();
+    if let Some(namespaces) = serialized.get("state").and_then(|state| state.as_object()) {
+        for (namespace, state) in namespaces {
+            let Some(state) = state.as_object().filter(|_| namespace != VISITED_NAMESPACE) else {
+                continue;
+            };
+            for (variable, value) in state {
+                let key = if namespace == kataru::GLOBAL {
+                    variable.clone()
+                } else {
+                    format!("{}:{}", namespace, variable)
+                };
+                dict.insert(key, json_to_variant(value));
+            }
+        }
+    }
+    Ok(dict)
+}
+
+/// The bookmark's state as `namespace -> variable -> value` maps.
+pub type StateMap = BTreeMap<String, serde_json::Map<String, serde_json::Value>>;
+
+/// Serializes the bookmark, returning it along with its state.
+pub fn serialize_state(bookmark: &Bookmark) -> InterfaceResult<(serde_json::Value, StateMap)> {
+    let serialized = match serde_json::to_value(bookmark) {
+        Ok(serialized) => serialized,
+        Err(err) => {
+            return Err(KataruError::new(
+                ERROR_RUNTIME,
+                format!("Could not serialize bookmark: {}", err),
+            ))
+        }
+    };
+    let state = serialized
+        .get("state")
+        .and_then(|state| serde_json::from_value(state.clone()).ok())
+        .unwrap_or_default();
+    Ok((serialized, state))
+}
+
+/// Edits the bookmark's state through its serialized form.
+pub fn edit_state(
+    bookmark: &Bookmark,
+    edit: impl FnOnce(&mut StateMap),
+) -> InterfaceResult<Bookmark> {
+    let (mut serialized, mut state) = serialize_state(bookmark)?;
+    edit(&mut state);
+    serialized["state"] = serde_json::to_value(state).unwrap_or_default();
+    match serde_json::from_value(serialized) {
+        Ok(bookmark) => Ok(bookmark),
+        Err(err) => Err(KataruError::new(
+            ERROR_VALIDATION,
+            format!("Could not rebuild edited bookmark: {}", err),
+        )),
+    }
+}
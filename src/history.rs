@@ -0,0 +1,199 @@
+//! A log of every line a runner has emitted, used for dialogue backlogs and rewinding.
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use godot::prelude::*;
+use kataru::*;
+use serde::{Deserialize, Serialize};
+
+use crate::convert::json_to_variant;
+use crate::lines::split_pinned_id;
+
+/// Oldest entries are dropped once a history grows past this many lines.
+const MAX_HISTORY_LEN: usize = 1000;
+
+/// Only this many of the most recent lines keep the bookmark needed to rewind to them,
+/// since each bookmark is a full copy of the story state.
+const MAX_REWIND_LEN: usize = 100;
+
+/// How the runner was advanced to produce a line, so it can be replayed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Cause {
+    /// `Runner::next` with the given input.
+    Next(String),
+    /// `Runner::run` on the given passage.
+    Run(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// One of `dialogue`, `choices`, `command`, `input`, `invalid_choice` or `end`.
+    pub kind: String,
+    pub passage: String,
+    pub speaker: String,
+    pub text: String,
+    pub attributes: serde_json::Value,
+    pub choices: Vec<String>,
+    /// The option picked for a `choices` line, once the player has chosen.
+    pub chosen: String,
+    pub cause: Cause,
+    /// The runner's bookmark from just before this line was produced,
+    /// or `None` once the line is too old to rewind to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bookmark: Option<Bookmark>,
+}
+
+impl HistoryEntry {
+    pub fn new(line: &Line, cause: Cause, bookmark: Bookmark, passage: &str) -> Self {
+        let mut entry = Self {
+            kind: String::new(),
+            passage: passage.to_string(),
+            speaker: String::new(),
+            text: String::new(),
+            attributes: serde_json::Value::Null,
+            choices: Vec::new(),
+            chosen: String::new(),
+            cause,
+            bookmark: Some(bookmark),
+        };
+        entry.kind = match line {
+            Line::Dialogue(dialogue) => {
+                entry.speaker = dialogue.name.to_string();
+                entry.text = split_pinned_id(&dialogue.text).0.to_string();
+                entry.attributes = serde_json::to_value(&dialogue.attributes).unwrap_or_default();
+                "dialogue"
+            }
+            Line::Choices(choices) => {
+                entry.choices = choices.choices.iter().map(|c| c.to_string()).collect();
+                "choices"
+            }
+            Line::Command(command) => {
+                entry.text = command.name.to_string();
+                entry.attributes = serde_json::to_value(&command.params).unwrap_or_default();
+                "command"
+            }
+            Line::Input(_) => "input",
+            Line::InvalidChoice => "invalid_choice",
+            Line::End => "end",
+        }
+        .to_string();
+        entry
+    }
+
+    pub fn to_dictionary(&self) -> Dictionary {
+        let mut dict = Dictionary::new();
+        dict.insert("kind", self.kind.clone());
+        dict.insert("passage", self.passage.clone());
+        dict.insert("speaker", self.speaker.clone());
+        dict.insert("text", self.text.clone());
+        dict.insert("attributes", json_to_variant(&self.attributes));
+        dict.insert(
+            "choices",
+            Array::<GodotString>::from_iter(self.choices.iter().map(|c| c.into())),
+        );
+        dict.insert("chosen", self.chosen.clone());
+        dict
+    }
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct History {
+    entries: VecDeque<HistoryEntry>,
+}
+
+impl History {
+    pub fn last(&self) -> Option<&HistoryEntry> {
+        self.entries.back()
+    }
+
+    pub fn push(&mut self, entry: HistoryEntry) {
+        if self.entries.len() >= MAX_HISTORY_LEN {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+        if let Some(index) = self.entries.len().checked_sub(MAX_REWIND_LEN + 1) {
+            self.entries[index].bookmark = None;
+        }
+    }
+
+    /// Marks `input` as the option chosen for the last line, if it offered choices.
+    pub fn choose(&mut self, input: &str) {
+        if let Some(entry) = self.entries.back_mut() {
+            if entry.kind == "choices" && !input.is_empty() {
+                entry.chosen = input.to_string();
+            }
+        }
+    }
+
+    /// Finds the dialogue or choices line `steps` such lines back, returning its index along with
+    /// how it was produced and the bookmark from before it, to replay it from.
+    /// Commands, inputs and the end aren't counted, and lines that no longer have a bookmark can't be replayed.
+    pub fn replay_point(&self, steps: usize) -> Option<(usize, Cause, Bookmark)> {
+        let shown: Vec<usize> = (0..self.entries.len())
+            .filter(|&i| matches!(self.entries[i].kind.as_str(), "dialogue" | "choices"))
+            .collect();
+        let index = *shown.get(shown.len().checked_sub(steps + 1)?)?;
+        let entry = &self.entries[index];
+        Some((index, entry.cause.clone(), entry.bookmark.clone()?))
+    }
+
+    /// Drops everything after the entry at `index`, once its line has been replayed.
+    pub fn rewind_to(&mut self, index: usize) {
+        self.entries.truncate(index + 1);
+        if let Some(entry) = self.entries.back_mut() {
+            entry.chosen.clear();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Returns the most recent `limit` entries, oldest first. A negative limit returns everything.
+    pub fn to_array(&self, limit: i64) -> Array<Dictionary> {
+        let skip = if limit < 0 {
+            0
+        } else {
+            self.entries.len().saturating_sub(limit as usize)
+        };
+        Array::from_iter(self.entries.iter().skip(skip).map(|e| e.to_dictionary()))
+    }
+
+    /// Path of the history file saved alongside the bookmark at `bookmark_path`.
+    pub fn path_for(bookmark_path: &Path) -> PathBuf {
+        bookmark_path.with_extension("history.json")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let source = match serde_json::to_string(self) {
+            Ok(source) => source,
+            Err(err) => return Err(error!("Could not serialize history: {}", err)),
+        };
+        if let Err(err) = fs::write(path, source) {
+            return Err(error!(
+                "Error writing history to '{}': {}",
+                path.display(),
+                err
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) => {
+                return Err(error!(
+                    "Error reading history from '{}': {}",
+                    path.display(),
+                    err
+                ))
+            }
+        };
+        match serde_json::from_str(&source) {
+            Ok(history) => Ok(history),
+            Err(err) => Err(error!("Could not parse history: {}", err)),
+        }
+    }
+}
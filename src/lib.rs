@@ -1,6 +1,9 @@
+use std::collections::HashMap;
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use glob::glob;
+use godot::engine::TranslationServer;
 use godot::prelude::*;
 use kataru::*;
 
@@ -13,17 +16,40 @@ pub type DebugLevel = u8;
 pub const DEBUG_NONE: u8 = 0;
 pub const DEBUG_INFO: u8 = 1;
 pub const DEBUG_VERBOSE: u8 = 2;
-mod codegen;
 
-fn last_modified_time(path: &PathBuf) -> Option<std::time::SystemTime> {
-    glob(path.to_str()?)
-        .expect("Couldn't access local directory")
-        .flatten() // Remove failed
-        .filter(|f| f.metadata().is_ok() && !f.metadata().unwrap().is_dir()) // Filter out directories (only consider files)
-        .map(|f| f.metadata().unwrap().modified().unwrap())
-        // Get the most recently modified file
-        .max()
-}
+/// Id of the runner driven by the plain `next`/`goto`/`run` calls.
+pub const MAIN_RUNNER: &str = "main";
+mod autosave;
+mod choices;
+mod codegen;
+mod commands;
+use autosave::{changed_passage, Autosave, AutosaveTrigger};
+use choices::{describe_choices, mark_visited, ChoiceInfo};
+use codegen::{CodegenTarget, CODEGEN_GDSCRIPT};
+use commands::{CommandRegistry, PendingCommands, PENDING_COMMAND};
+mod convert;
+mod history;
+mod lines;
+mod localize;
+mod migrate;
+mod playback;
+mod reload;
+mod skip;
+mod slots;
+mod snapshot;
+mod status;
+mod watcher;
+use convert::{
+    attributes_to_array, bookmark_state_to_dict, params_to_dict, serde_to_variant, val_to_variant,
+    value_type_name, variant_to_json, variant_to_val,
+};
+use history::{Cause, History, HistoryEntry};
+use migrate::{Migrations, SaveVersion};
+use playback::{Autoplay, AutoplaySettings, ChoiceTimeout};
+use skip::{LineState, ReadLines, SkipMode, MAX_SKIPPED_LINES, SKIP_ALL, SKIP_OFF};
+use slots::{write_atomic, AtomicWrites, SlotMeta};
+use status::*;
+use watcher::StoryWatcher;
 
 #[derive(GodotClass)]
 #[class(base=Node)]
@@ -32,13 +58,69 @@ pub struct KataruInterface {
     story_path: PathBuf,
     bookmark_path: PathBuf,
     codegen_path: PathBuf,
+    codegen_targets: CodegenTarget,
     default_passage: String,
+    // Passage to restart from when hot reload removes the current passage.
+    reload_fallback_passage: String,
     debug_level: u8,
+    // The compiled story, for looking up passages and config.
+    story: Option<Story>,
+    // Hash of the story's content, recorded in saves to detect when they need migrating.
+    story_hash: String,
+    // Renames applied to saves from older versions of the story, and a callable for anything else.
+    migrations_path: PathBuf,
+    migrations: Migrations,
+    migration_callback: Option<Callable>,
+    // Every runner shares one kataru `Runner`, which takes its own copy of the story.
+    // The bookmark of whichever runner id is being driven is swapped into it.
     runner: Option<Runner>,
-    watch_dir: Option<PathBuf>,
-    watch_poll_time: f64,
+    // The runner id whose bookmark is loaded into `runner`.
+    active_runner: String,
+    // Bookmarks of every other runner, by runner id.
+    bookmarks: HashMap<String, Bookmark>,
+    // Bookmark paths for runners created with `create_runner`.
+    runner_bookmark_paths: HashMap<String, PathBuf>,
+    histories: HashMap<String, History>,
+    commands: CommandRegistry,
+    // Whether commands without a registered callable are errors.
+    strict_commands: bool,
+    // Commands each runner is waiting on, by runner id.
+    pending_commands: HashMap<String, PendingCommands>,
+    // Whether runners advance on their own once their pending commands complete.
+    advance_after_commands: bool,
+    // Runners with auto-play enabled, by runner id.
+    autoplay: HashMap<String, Autoplay>,
+    autoplay_settings: AutoplaySettings,
+    // Deadlines on timed choices, by runner id.
+    choice_timeouts: HashMap<String, ChoiceTimeout>,
+    // State variables requested by the current input line, by runner id.
+    pending_inputs: HashMap<String, Vec<String>>,
+    // The choices each runner is currently offering, by runner id.
+    current_choices: HashMap<String, Vec<ChoiceInfo>>,
+    // Where strings are exported for translation when the story compiles.
+    strings_path: PathBuf,
+    // The locale the story is written in, and the one to translate it to.
+    source_locale: String,
+    locale: String,
+    // Lines the player has read, shared by every runner and save.
+    read_lines_path: PathBuf,
+    read_lines: ReadLines,
+    // Skip modes, and what each runner's current line means for skipping, by runner id.
+    skip_modes: HashMap<String, SkipMode>,
+    line_states: HashMap<String, LineState>,
+    // Directory that save slots are kept in.
+    slots_path: PathBuf,
+    // Seconds played, carried over by save slots.
+    playtime: f64,
+    // When the main runner is autosaved, and which slot is next.
+    autosave: Autosave,
+    // Whether `save` and `load` also persist the main runner's history.
+    persist_history: bool,
+    watcher: Option<StoryWatcher>,
+    // Seconds without new file events before the story is recompiled.
     watch_poll_interval: f64,
-    modified_time: Option<std::time::SystemTime>,
+    // Error from the most recent call, along with the call that produced it.
+    last_error: Option<(KataruError, String)>,
 
     #[base]
     base: Base<Node>,
@@ -52,45 +134,48 @@ impl NodeVirtual for KataruInterface {
             story_path: "".into(),
             bookmark_path: "".into(),
             codegen_path: "".into(),
+            codegen_targets: CODEGEN_GDSCRIPT,
             default_passage: "".to_string(),
+            reload_fallback_passage: "".to_string(),
+            story: None,
+            story_hash: String::new(),
+            migrations_path: "".into(),
+            migrations: Migrations::default(),
+            migration_callback: None,
             runner: None,
-            watch_dir: None,
-            watch_poll_time: 0.0,
+            active_runner: MAIN_RUNNER.to_string(),
+            bookmarks: HashMap::new(),
+            runner_bookmark_paths: HashMap::new(),
+            histories: HashMap::new(),
+            commands: CommandRegistry::default(),
+            strict_commands: false,
+            pending_commands: HashMap::new(),
+            advance_after_commands: false,
+            autoplay: HashMap::new(),
+            autoplay_settings: AutoplaySettings::default(),
+            choice_timeouts: HashMap::new(),
+            pending_inputs: HashMap::new(),
+            current_choices: HashMap::new(),
+            strings_path: "".into(),
+            source_locale: "en".to_string(),
+            locale: "".to_string(),
+            read_lines_path: "".into(),
+            read_lines: ReadLines::default(),
+            skip_modes: HashMap::new(),
+            line_states: HashMap::new(),
+            slots_path: "".into(),
+            playtime: 0.0,
+            autosave: Autosave::default(),
+            persist_history: false,
+            watcher: None,
             watch_poll_interval: 0.0,
-            modified_time: None,
             debug_level: DEBUG_NONE,
+            last_error: None,
             base,
         }
     }
 }
 
-fn serde_to_json<T: serde::Serialize>(value: &T) -> Variant {
-    Variant::from(serde_json::to_string(value).unwrap())
-}
-
-fn variant_to_val(variant: Variant) -> Result<Value> {
-    Ok(match variant.get_type() {
-        VariantType::Bool => Value::Bool(variant.to::<bool>()),
-        VariantType::Float => Value::Number(variant.to::<f64>()),
-        VariantType::String => Value::String(variant.to::<String>()),
-        VariantType::StringName => Value::String(variant.to::<String>()),
-        _ => {
-            return Err(error!(
-                "Variant could not be converted to a Kataru value: {}",
-                variant
-            ))
-        }
-    })
-}
-
-fn val_to_variant(value: &Value) -> Variant {
-    match value {
-        Value::Bool(b) => Variant::from(b.clone()),
-        Value::Number(f) => Variant::from(f.clone()),
-        Value::String(s) => Variant::from(s.clone()),
-    }
-}
-
 /// Logs a fatal assertion by sending a signal to Godot.
 /// Can only be called from a struct that implements NodeVirtual that has a FATAL signal set up.
 #[macro_export]
@@ -105,6 +190,8 @@ impl KataruInterface {
     /// Initialize kataru with the given path settings.
     /// This *must* be called before any other methods are called.
     /// If `story_src_path` is specified, compile the story to in `story_src_path` to `story_path`.
+    /// Constants are generated to `codegen_path` for each language in the `codegen_targets` bitflags.
+    /// Returns the kind of error that occurred, or `ERROR_NONE`.
     #[func]
     pub fn init(
         &mut self,
@@ -112,14 +199,16 @@ impl KataruInterface {
         story_path: GodotString,
         bookmark_path: GodotString,
         codegen_path: GodotString,
+        codegen_targets: CodegenTarget,
         default_passage: GodotString,
         debug_level: DebugLevel,
         watch_poll_interval: f64,
-    ) {
+    ) -> ErrorKind {
         self.story_src_path = story_src_path.to_string().into();
         self.story_path = story_path.to_string().into();
         self.bookmark_path = bookmark_path.to_string().into();
         self.codegen_path = codegen_path.to_string().into();
+        self.codegen_targets = codegen_targets;
         self.default_passage = default_passage.into();
         self.debug_level = debug_level;
         self.watch_poll_interval = watch_poll_interval;
@@ -127,139 +216,756 @@ impl KataruInterface {
         if self.debug_level >= DEBUG_INFO {
             godot_print!("Kataru.init()");
         }
-        if let Err(err) = self.try_init() {
+        let result = self.try_init();
+        self.report_init(result)
+    }
+    // Init failures are fatal, since nothing else can run without a story.
+    fn report_init(&mut self, result: InterfaceResult<()>) -> ErrorKind {
+        if let Err(err) = &result {
             godot_fatal!(self, "Kataru.init(): {}", err);
         }
+        self.report("Kataru.init()".to_string(), result)
     }
-    fn try_init(&mut self) -> Result<()> {
-        // Validate and compile if a source path is specified.
-        let story = if !self.story_src_path.as_os_str().is_empty() {
-            let story = Story::load(&self.story_src_path)?;
-            let mut bookmark = Bookmark::load_or_default(
-                &self.bookmark_path,
-                &story,
-                self.default_passage.clone(),
-            )?;
-            Validator::new(&story, &mut bookmark).validate()?;
-            story.save(&self.story_path)?;
+    fn try_init(&mut self) -> InterfaceResult<()> {
+        let compile = !self.story_src_path.as_os_str().is_empty();
+        // Watch the sources first, so a story that fails to load is loaded again once it's fixed.
+        if compile && self.watcher.is_none() {
+            self.watcher = Some(StoryWatcher::new(&self.story_src_path).with_kind(ERROR_IO)?);
+        }
+        // Saves from older versions of the story are migrated, so renames must be loaded first.
+        self.load_migrations()?;
+        let story = if compile {
+            Story::load(&self.story_src_path).with_kind(ERROR_PARSE)?
+        } else {
+            require_file(&self.story_path)?;
+            Story::load(&self.story_path).with_kind(ERROR_PARSE)?
+        };
+        let bookmark = self.load_saved_bookmark(&self.bookmark_path, &story)?;
+
+        // Validate against the migrated bookmark and compile if a source path is specified.
+        if compile {
+            self.try_compile(&story, &bookmark)?;
+        }
+
+        // Load the main runner into the shared runner, and park the other runners' bookmarks.
+        let mut bookmarks = HashMap::with_capacity(self.runner_bookmark_paths.len());
+        for (id, bookmark_path) in &self.runner_bookmark_paths {
+            bookmarks.insert(id.clone(), self.load_saved_bookmark(bookmark_path, &story)?);
+        }
+        self.runner =
+            Some(Runner::init(bookmark, story.clone(), false).with_kind(ERROR_VALIDATION)?);
+        self.active_runner = MAIN_RUNNER.to_string();
+        self.bookmarks = bookmarks;
+        if self.read_lines_path.exists() {
+            self.read_lines = ReadLines::load(&self.read_lines_path).with_kind(ERROR_IO)?;
+        }
+        self.commands.declare(&story);
+        self.story_hash = migrate::story_hash(&story);
+        self.story = Some(story);
+        self.base.emit_signal(Self::LOADED.into(), &[]);
+        Ok(())
+    }
+    // Validate the story loaded from source against `bookmark`, then compile it and generate constants.
+    fn try_compile(&mut self, story: &Story, bookmark: &Bookmark) -> InterfaceResult<()> {
+        let mut bookmark = bookmark.clone();
+        bookmark.init_state(story);
+        Validator::new(story, &mut bookmark)
+            .validate()
+            .with_kind(ERROR_VALIDATION)?;
+        story.save(&self.story_path).with_kind(ERROR_IO)?;
+        if self.debug_level >= DEBUG_INFO {
+            godot_print!(
+                "Kataru.init(): story compiled to {}",
+                self.story_path.display()
+            )
+        }
+
+        // Generate constants if enabled.
+        if !self.codegen_path.as_os_str().is_empty() {
+            codegen::try_codegen_consts(&self.codegen_path, story, self.codegen_targets)
+                .with_kind(ERROR_IO)?;
+
             if self.debug_level >= DEBUG_INFO {
                 godot_print!(
-                    "Kataru.init(): story compiled to {}",
-                    self.story_path.display()
+                    "Kataru.init(): constants files generated to {}",
+                    self.codegen_path.display()
                 )
             }
+        }
 
-            // Generate constants if enabled.
-            if !self.codegen_path.as_os_str().is_empty() {
-                codegen::try_codegen_consts(&self.codegen_path, &story)?;
+        // Extract strings for translation if enabled.
+        if !self.strings_path.as_os_str().is_empty() {
+            localize::try_export_strings(&self.strings_path, story, &self.source_locale)
+                .with_kind(ERROR_IO)?;
 
-                if self.debug_level >= DEBUG_INFO {
-                    godot_print!(
-                        "Kataru.init(): constants files generated to {}",
-                        self.codegen_path.display()
-                    )
-                }
+            if self.debug_level >= DEBUG_INFO {
+                godot_print!(
+                    "Kataru.init(): strings extracted to {}",
+                    self.strings_path.display()
+                )
             }
+        }
+        Ok(())
+    }
+
+    fn load_migrations(&mut self) -> InterfaceResult<()> {
+        self.migrations = if self.migrations_path.exists() {
+            Migrations::load(&self.migrations_path)?
+        } else {
+            Migrations::default()
+        };
+        Ok(())
+    }
+
+    /// Set the YAML file of passage and state renames applied to saves from older versions of the story.
+    /// Must be called before `init`.
+    #[func]
+    pub fn set_migrations_path(&mut self, path: GodotString) {
+        self.migrations_path = path.to_string().into();
+    }
 
-            self.watch_dir = Some(Path::new(&self.story_src_path).join("**").join("*"));
-            self.modified_time = last_modified_time(self.watch_dir.as_ref().unwrap());
+    /// Set a callable run on saves from older versions of the story, after renames are applied.
+    /// It's called with the bookmark as a dictionary and a report of its `missing_passage` and
+    /// `missing_variables`, and can return a fixed bookmark dictionary, or null to keep it as is.
+    #[func]
+    pub fn set_migration_callback(&mut self, callable: Callable) {
+        self.migration_callback = Some(callable);
+    }
 
-            story
+    // Carries a bookmark saved with the story hashed as `saved_hash` over to the current story.
+    // Variables that no longer exist are dropped, but a missing passage is an error.
+    fn migrate_bookmark(
+        &self,
+        story: &Story,
+        bookmark: Bookmark,
+        saved_hash: Option<String>,
+    ) -> InterfaceResult<Bookmark> {
+        let story_hash = migrate::story_hash(story);
+        if saved_hash.as_deref() == Some(story_hash.as_str()) {
+            return Ok(bookmark);
+        }
+        let mut bookmark = self.migrations.apply(&bookmark)?;
+        let mut compatibility = migrate::check(&bookmark, story)?;
+        if let Some(callback) = &self.migration_callback {
+            let mut report = compatibility.to_dictionary();
+            report.insert("saved_story_hash", saved_hash.unwrap_or_default());
+            report.insert("story_hash", story_hash);
+            let mut args = VariantArray::new();
+            args.push(serde_to_variant(&bookmark));
+            args.push(Variant::from(report));
+            let migrated = callback.callv(args);
+            if migrated.get_type() == VariantType::Dictionary {
+                bookmark = migrate::bookmark_from_json(variant_to_json(&migrated))?;
+                compatibility = migrate::check(&bookmark, story)?;
+            }
+        }
+        if let Some(passage) = &compatibility.missing_passage {
+            return Err(KataruError::new(
+                ERROR_VALIDATION,
+                format!(
+                    "Saved passage '{}' no longer exists. Rename it in the migrations file or the migration callback.",
+                    passage
+                ),
+            ));
+        }
+        if !compatibility.missing_variables.is_empty() {
+            if self.debug_level >= DEBUG_INFO {
+                godot_print!(
+                    "Kataru: dropping variables missing from the story: {:?}",
+                    compatibility.missing_variables
+                );
+            }
+            bookmark = migrate::remove_missing_variables(&bookmark, &compatibility)?;
+        }
+        // Variables added since the save was made start at their defaults.
+        bookmark.init_state(story);
+        Ok(bookmark)
+    }
+
+    /// Set where dialogue and choice strings are extracted to for translation, and the locale
+    /// the story is written in. Must be called before `init`.
+    #[func]
+    pub fn set_localization(&mut self, strings_path: GodotString, source_locale: GodotString) {
+        self.strings_path = strings_path.to_string().into();
+        self.source_locale = source_locale.to_string();
+    }
+
+    /// Set the locale dialogue and choices are translated to.
+    /// Strings are looked up in Godot's translations for the locale, falling back to the story's text.
+    #[func]
+    pub fn set_locale(&mut self, locale: GodotString) {
+        if self.debug_level >= DEBUG_INFO {
+            godot_print!("Kataru.set_locale('{}')", locale);
+        }
+        self.locale = locale.to_string();
+    }
+
+    #[func]
+    pub fn get_locale(&self) -> GodotString {
+        self.locale.clone().into()
+    }
+
+    // Translates `text` said by the runner named `id` into the current locale with the
+    // translations Godot imported from the string table, stripping any pinned id.
+    fn translate(&self, id: &str, text: &str) -> String {
+        let (stripped, _) = lines::split_pinned_id(text);
+        if self.locale.is_empty() || self.locale == self.source_locale {
+            return stripped.to_string();
+        }
+        let Ok(bookmark) = self.bookmark(id) else {
+            return stripped.to_string();
+        };
+        let line_id = lines::line_id(
+            bookmark.namespace(),
+            bookmark.passage(),
+            bookmark.line(),
+            text,
+        );
+        let Some(translation) =
+            TranslationServer::singleton().get_translation_object(self.locale.clone().into())
+        else {
+            return stripped.to_string();
+        };
+        let translated = translation
+            .get_message(StringName::from(line_id.as_str()))
+            .to_string();
+        if translated.is_empty() {
+            stripped.to_string()
+        } else {
+            translated
+        }
+    }
+
+    /// Recompile the story and carry every live runner over to it, keeping their
+    /// position and state where possible. Returns whether the main runner's position was preserved.
+    fn try_reload(&mut self) -> InterfaceResult<bool> {
+        let story = Story::load(&self.story_src_path).with_kind(ERROR_PARSE)?;
+        let bookmark = self.bookmark(MAIN_RUNNER)?.clone();
+        self.try_compile(&story, &bookmark)?;
+        let fallback_passage = if self.reload_fallback_passage.is_empty() {
+            self.default_passage.clone()
         } else {
-            Story::load(&self.story_path)?
+            self.reload_fallback_passage.clone()
         };
 
-        // Load bookmark.
-        let bookmark = if self.bookmark_path.as_os_str().is_empty() {
+        let mut bookmarks = self.bookmarks.clone();
+        if let Some(runner) = &self.runner {
+            bookmarks.insert(self.active_runner.clone(), runner.bookmark().clone());
+        }
+        let mut position_preserved = true;
+        for (id, bookmark) in &mut bookmarks {
+            let preserved = reload::remap_bookmark(bookmark, &story, &fallback_passage);
+            if id == MAIN_RUNNER {
+                position_preserved = preserved;
+            }
+        }
+        let bookmark = bookmarks.remove(MAIN_RUNNER).unwrap_or_default();
+        self.runner =
+            Some(Runner::init(bookmark, story.clone(), false).with_kind(ERROR_VALIDATION)?);
+        self.active_runner = MAIN_RUNNER.to_string();
+        self.bookmarks = bookmarks;
+        // Remapped runners restart their line, so whatever they were waiting on is stale.
+        self.current_choices.clear();
+        self.choice_timeouts.clear();
+        self.pending_inputs.clear();
+        self.line_states.clear();
+        self.pending_commands.clear();
+        for autoplay in self.autoplay.values_mut() {
+            autoplay.reset();
+        }
+        self.load_migrations()?;
+        self.commands.declare(&story);
+        self.story_hash = migrate::story_hash(&story);
+        self.story = Some(story);
+        Ok(position_preserved)
+    }
+
+    /// Set the passage that hot reload restarts from when the current passage was removed.
+    /// Defaults to `default_passage` when empty.
+    #[func]
+    pub fn set_reload_fallback_passage(&mut self, passage: GodotString) {
+        self.reload_fallback_passage = passage.to_string();
+    }
+
+    fn load_bookmark(&self, bookmark_path: &PathBuf, story: &Story) -> InterfaceResult<Bookmark> {
+        if bookmark_path.as_os_str().is_empty() {
             // If no path provided, just use default bookmark.
-            Bookmark::default()
+            Ok(Bookmark::default())
         } else if self.default_passage.is_empty() {
             // If a path is provided but no default passage, then don't try to generate a bookmark.
-            Bookmark::load(&self.bookmark_path)?
+            require_file(bookmark_path)?;
+            Bookmark::load(bookmark_path).with_kind(ERROR_PARSE)
         } else {
             // Otherwise, try loading a bookmark and fallback to making a default.
-            Bookmark::load_or_default(&self.bookmark_path, &story, self.default_passage.clone())?
+            Bookmark::load_or_default(bookmark_path, story, self.default_passage.clone())
+                .with_kind(ERROR_PARSE)
+        }
+    }
+
+    // Loads the bookmark saved at `bookmark_path`, migrating it if it was saved with another
+    // version of `story`. Falls back to a fresh bookmark like `load_bookmark` if there's no save.
+    fn load_saved_bookmark(
+        &self,
+        bookmark_path: &PathBuf,
+        story: &Story,
+    ) -> InterfaceResult<Bookmark> {
+        let saved = !bookmark_path.as_os_str().is_empty() && bookmark_path.exists();
+        let bookmark = self.load_bookmark(bookmark_path, story)?;
+        if !saved {
+            return Ok(bookmark);
+        }
+        let version = SaveVersion::load_for(bookmark_path).with_kind(ERROR_PARSE)?;
+        self.migrate_bookmark(story, bookmark, version.map(|v| v.story_hash))
+    }
+
+    fn no_runner(id: &str) -> KataruError {
+        KataruError::new(ERROR_RUNTIME, format!("No runner with id '{}'.", id))
+    }
+    // The bookmark of the runner named `id`.
+    fn bookmark(&self, id: &str) -> InterfaceResult<&Bookmark> {
+        let Some(runner) = &self.runner else {
+            return Err(KataruError::uninitialized());
         };
+        if id == self.active_runner {
+            return Ok(runner.bookmark());
+        }
+        self.bookmarks.get(id).ok_or_else(|| Self::no_runner(id))
+    }
+    // The shared runner, with the bookmark of the runner named `id` swapped in.
+    fn runner_mut(&mut self, id: &str) -> InterfaceResult<&mut Runner> {
+        let Some(runner) = &mut self.runner else {
+            return Err(KataruError::uninitialized());
+        };
+        if id != self.active_runner {
+            let Some(bookmark) = self.bookmarks.get(id).cloned() else {
+                return Err(Self::no_runner(id));
+            };
+            let parked = runner.bookmark().clone();
+            runner.load_bookmark(bookmark).with_kind(ERROR_RUNTIME)?;
+            self.bookmarks.remove(id);
+            let previous = std::mem::replace(&mut self.active_runner, id.to_string());
+            self.bookmarks.insert(previous, parked);
+        }
+        Ok(runner)
+    }
+    // Replaces the bookmark of the runner named `id`, adding the runner if it doesn't exist.
+    fn set_bookmark(&mut self, id: &str, bookmark: Bookmark) -> InterfaceResult<()> {
+        match &mut self.runner {
+            None => Err(KataruError::uninitialized()),
+            Some(runner) if id == self.active_runner => {
+                runner.load_bookmark(bookmark).with_kind(ERROR_RUNTIME)
+            }
+            Some(_) => {
+                self.bookmarks.insert(id.to_string(), bookmark);
+                Ok(())
+            }
+        }
+    }
 
-        // Load runner from compiled story.
-        self.runner = Some(Runner::init(bookmark, story, false)?);
-        self.base.emit_signal(Self::LOADED.into(), &[]);
+    /// Records the outcome of a call and reports any failure to GDScript
+    /// through the `error` signal and `last_error`.
+    fn report<T>(&mut self, context: String, result: InterfaceResult<T>) -> ErrorKind {
+        match result {
+            Ok(_) => {
+                self.last_error = None;
+                ERROR_NONE
+            }
+            Err(err) => {
+                godot_error!("{}: {}", context, err);
+                let kind = err.kind;
+                self.base.emit_signal(
+                    Self::ERROR.into(),
+                    &[
+                        Variant::from(kind as i64),
+                        Variant::from(err.message.clone()),
+                        Variant::from(context.clone()),
+                    ],
+                );
+                self.last_error = Some((err, context));
+                kind
+            }
+        }
+    }
+
+    /// Message of the error from the most recent call, or empty if it succeeded.
+    #[func]
+    pub fn last_error(&self) -> GodotString {
+        match &self.last_error {
+            Some((err, _context)) => err.message.clone().into(),
+            None => GodotString::new(),
+        }
+    }
+    /// Kind of the error from the most recent call, or `ERROR_NONE` if it succeeded.
+    #[func]
+    pub fn last_error_kind(&self) -> ErrorKind {
+        match &self.last_error {
+            Some((err, _context)) => err.kind,
+            None => ERROR_NONE,
+        }
+    }
+    /// The call that produced the most recent error, or empty if it succeeded.
+    #[func]
+    pub fn last_error_context(&self) -> GodotString {
+        match &self.last_error {
+            Some((_err, context)) => context.clone().into(),
+            None => GodotString::new(),
+        }
+    }
+
+    /// Create a new runner named `id` sharing the loaded story.
+    /// Its bookmark is loaded from `bookmark_path` (or defaulted if empty).
+    /// Replaces any existing runner with the same id.
+    #[func]
+    pub fn create_runner(&mut self, id: GodotString, bookmark_path: GodotString) -> ErrorKind {
+        if self.debug_level >= DEBUG_INFO {
+            godot_print!("Kataru.create_runner('{}', '{}')", id, bookmark_path);
+        }
+        let result = self.try_create_runner(id.to_string(), bookmark_path.to_string().into());
+        self.report(format!("Kataru.create_runner('{}')", id), result)
+    }
+    fn try_create_runner(&mut self, id: String, bookmark_path: PathBuf) -> InterfaceResult<()> {
+        if id == MAIN_RUNNER {
+            return Err(KataruError::new(
+                ERROR_RUNTIME,
+                format!("Runner id '{}' is reserved.", MAIN_RUNNER),
+            ));
+        }
+        let Some(story) = &self.story else {
+            return Err(KataruError::uninitialized());
+        };
+        let bookmark = self.load_saved_bookmark(&bookmark_path, story)?;
+        self.set_bookmark(&id, bookmark)?;
+        self.runner_bookmark_paths.insert(id, bookmark_path);
+        Ok(())
+    }
+
+    /// Remove the runner named `id`. The main runner cannot be removed.
+    #[func]
+    pub fn remove_runner(&mut self, id: GodotString) -> ErrorKind {
+        if self.debug_level >= DEBUG_INFO {
+            godot_print!("Kataru.remove_runner('{}')", id);
+        }
+        let result = self.try_remove_runner(id.to_string());
+        self.report(format!("Kataru.remove_runner('{}')", id), result)
+    }
+    fn try_remove_runner(&mut self, id: String) -> InterfaceResult<()> {
+        if id == MAIN_RUNNER {
+            return Err(KataruError::new(
+                ERROR_RUNTIME,
+                "Cannot remove the main runner.",
+            ));
+        }
+        if id == self.active_runner {
+            self.runner_mut(MAIN_RUNNER)?;
+        }
+        self.bookmarks.remove(&id);
+        self.runner_bookmark_paths.remove(&id);
+        self.histories.remove(&id);
+        self.pending_commands.remove(&id);
+        self.autoplay.remove(&id);
+        self.choice_timeouts.remove(&id);
+        self.pending_inputs.remove(&id);
+        self.current_choices.remove(&id);
+        self.skip_modes.remove(&id);
+        self.line_states.remove(&id);
         Ok(())
     }
 
+    /// Returns true if a runner named `id` exists.
+    #[func]
+    pub fn has_runner(&self, id: GodotString) -> bool {
+        self.bookmark(&id.to_string()).is_ok()
+    }
+
     /// Run the next line of dialogue.
     #[func]
-    pub fn next(&mut self, input: GodotString) {
+    pub fn next(&mut self, input: GodotString) -> ErrorKind {
+        self.next_on(MAIN_RUNNER.into(), input)
+    }
+    /// Run the next line of dialogue on the runner named `id`.
+    #[func]
+    pub fn next_on(&mut self, id: GodotString, input: GodotString) -> ErrorKind {
         if self.debug_level >= DEBUG_INFO {
-            godot_print!("Kataru.next('{}')", input);
+            godot_print!("Kataru.next_on('{}', '{}')", id, input);
         }
-        if let Err(err) = self.try_next(input.to_string()) {
-            godot_error!("Kataru.next('{}'): {}", input, err);
+        // Hold the line until the runner's pending commands complete.
+        if let Some(pending) = self.pending_commands.get_mut(&id.to_string()) {
+            pending.deferred_input = Some(input.to_string());
+            if self.debug_level >= DEBUG_INFO {
+                godot_print!(
+                    "Kataru.next_on('{}'): waiting on {} command(s)",
+                    id,
+                    pending.count
+                );
+            }
+            return ERROR_NONE;
         }
+        let result = self.try_next(&id.to_string(), input.to_string());
+        self.report(format!("Kataru.next_on('{}', '{}')", id, input), result)
     }
-    fn try_next(&mut self, input: String) -> Result<Line> {
-        if let Some(runner) = &mut self.runner {
-            let line = runner.next(&input)?;
+    fn try_next(&mut self, id: &str, input: String) -> InterfaceResult<Line> {
+        self.check_not_waiting(id)?;
+        let debug_level = self.debug_level;
+        let runner = self.runner_mut(id)?;
+        let snapshot = runner.bookmark().clone();
+        let line = runner.next(&input).with_kind(ERROR_RUNTIME)?;
+        let passage_changed = changed_passage(&snapshot, runner.bookmark());
 
-            if self.debug_level >= DEBUG_VERBOSE {
-                godot_print!("Kataru.next('{}'): {:#?}", input, runner.bookmark());
+        if debug_level >= DEBUG_VERBOSE {
+            godot_print!("Kataru.next('{}'): {:#?}", input, runner.bookmark());
+        }
+        let mut chose = false;
+        if !matches!(line, Line::InvalidChoice) {
+            self.histories
+                .entry(id.to_string())
+                .or_default()
+                .choose(&input);
+            chose = self
+                .current_choices
+                .get(id)
+                .is_some_and(|choices| choices.iter().any(|c| c.id == input));
+            if chose {
+                let runner = self.runner_mut(id)?;
+                let bookmark = mark_visited(runner.bookmark(), &snapshot, &input)?;
+                runner.load_bookmark(bookmark).with_kind(ERROR_RUNTIME)?;
             }
-            self.emit_line_signal(&line);
-            Ok(line)
-        } else {
-            Err(error!("Kataru was not initialized."))
         }
+        self.record_history(id, &line, Cause::Next(input), snapshot);
+        self.autosave_line(id, &line, passage_changed, chose);
+        self.emit_line_signal(id, &line)?;
+        Ok(line)
     }
 
-    /// Go to the given `passage`, but do not run the first line.
+    /// Pick the choice at `index` in the main runner's `choices` payload.
+    #[func]
+    pub fn choose(&mut self, index: i64) -> ErrorKind {
+        self.choose_on(MAIN_RUNNER.into(), index)
+    }
+    /// Pick the choice at `index` in the `choices` payload of the runner named `id`.
+    #[func]
+    pub fn choose_on(&mut self, id: GodotString, index: i64) -> ErrorKind {
+        if self.debug_level >= DEBUG_INFO {
+            godot_print!("Kataru.choose_on('{}', {})", id, index);
+        }
+        let result = self.try_choose(&id.to_string(), index);
+        self.report(format!("Kataru.choose_on('{}', {})", id, index), result)
+    }
+    fn try_choose(&mut self, id: &str, index: i64) -> InterfaceResult<()> {
+        let choices = self.current_choices(id)?;
+        let Some(choice) = usize::try_from(index).ok().and_then(|i| choices.get(i)) else {
+            return Err(KataruError::new(
+                ERROR_VALIDATION,
+                format!(
+                    "Choice index {} is out of range for {} choices.",
+                    index,
+                    choices.len()
+                ),
+            ));
+        };
+        let choice_id = choice.id.clone();
+        self.try_choose_id(id, choice_id)
+    }
+
+    /// Pick the choice with the given `choice_id` on the main runner.
+    #[func]
+    pub fn choose_id(&mut self, choice_id: GodotString) -> ErrorKind {
+        self.choose_id_on(MAIN_RUNNER.into(), choice_id)
+    }
+    /// Pick the choice with the given `choice_id` on the runner named `id`.
     #[func]
-    pub fn goto(&mut self, passage: GodotString) {
+    pub fn choose_id_on(&mut self, id: GodotString, choice_id: GodotString) -> ErrorKind {
         if self.debug_level >= DEBUG_INFO {
-            godot_print!("Kataru.goto({})", passage);
+            godot_print!("Kataru.choose_id_on('{}', '{}')", id, choice_id);
         }
-        if let Err(err) = self.try_goto(passage.to_string()) {
-            godot_error!("Kataru.goto({}): {}", passage, err);
+        let result = self.try_choose_id(&id.to_string(), choice_id.to_string());
+        self.report(
+            format!("Kataru.choose_id_on('{}', '{}')", id, choice_id),
+            result,
+        )
+    }
+    fn try_choose_id(&mut self, id: &str, choice_id: String) -> InterfaceResult<()> {
+        let choices = self.current_choices(id)?;
+        let Some(choice) = choices.iter().find(|choice| choice.id == choice_id) else {
+            return Err(KataruError::new(
+                ERROR_VALIDATION,
+                format!("'{}' is not one of the current choices.", choice_id),
+            ));
+        };
+        if !choice.available {
+            return Err(KataruError::new(
+                ERROR_VALIDATION,
+                format!("Choice '{}' is not available.", choice_id),
+            ));
         }
+        self.try_next(id, choice_id)?;
+        Ok(())
     }
-    fn try_goto(&mut self, passage: String) -> Result<()> {
-        if let Some(runner) = self.runner.as_mut() {
-            runner.goto(passage)
-        } else {
-            godot_fatal!(self, "Kataru was not initialized before call to goto.");
-            Err(error!("Kataru uninitialized."))
+
+    // The choices the runner named `id` is waiting on.
+    fn current_choices(&self, id: &str) -> InterfaceResult<&Vec<ChoiceInfo>> {
+        self.bookmark(id)?;
+        match self.current_choices.get(id) {
+            Some(choices) => Ok(choices),
+            None => Err(KataruError::new(
+                ERROR_RUNTIME,
+                format!("Runner '{}' is not waiting on choices.", id),
+            )),
         }
     }
 
+    /// Go to the given `passage`, but do not run the first line.
+    #[func]
+    pub fn goto(&mut self, passage: GodotString) -> ErrorKind {
+        self.goto_on(MAIN_RUNNER.into(), passage)
+    }
+    /// Go to the given `passage` on the runner named `id`, but do not run the first line.
+    #[func]
+    pub fn goto_on(&mut self, id: GodotString, passage: GodotString) -> ErrorKind {
+        if self.debug_level >= DEBUG_INFO {
+            godot_print!("Kataru.goto_on('{}', {})", id, passage);
+        }
+        let result = self.try_goto(&id.to_string(), passage.to_string());
+        self.report(format!("Kataru.goto_on('{}', {})", id, passage), result)
+    }
+    fn try_goto(&mut self, id: &str, passage: String) -> InterfaceResult<()> {
+        self.check_not_waiting(id)?;
+        self.runner_mut(id)?
+            .goto(passage)
+            .with_kind(ERROR_RUNTIME)?;
+        self.choice_timeouts.remove(id);
+        self.pending_inputs.remove(id);
+        self.current_choices.remove(id);
+        self.line_states.remove(id);
+        Ok(())
+    }
+
     /// Run the first line in the given `passage`.
     #[func]
-    pub fn run(&mut self, passage: GodotString) {
+    pub fn run(&mut self, passage: GodotString) -> ErrorKind {
+        self.run_on(MAIN_RUNNER.into(), passage)
+    }
+    /// Run the first line in the given `passage` on the runner named `id`.
+    #[func]
+    pub fn run_on(&mut self, id: GodotString, passage: GodotString) -> ErrorKind {
         if self.debug_level >= DEBUG_INFO {
-            godot_print!("Kataru.run('{}')", passage);
+            godot_print!("Kataru.run_on('{}', '{}')", id, passage);
         }
-        if let Err(err) = self.try_run(passage.to_string()) {
-            godot_error!("Kataru.run('{}'): {}", passage, err)
+        let result = self.try_run(&id.to_string(), passage.to_string());
+        self.report(format!("Kataru.run_on('{}', '{}')", id, passage), result)
+    }
+    fn try_run(&mut self, id: &str, passage: String) -> InterfaceResult<()> {
+        self.check_not_waiting(id)?;
+        let debug_level = self.debug_level;
+        let runner = self.runner_mut(id)?;
+        let snapshot = runner.bookmark().clone();
+        let line = runner.run(passage.clone()).with_kind(ERROR_RUNTIME)?;
+        let passage_changed = changed_passage(&snapshot, runner.bookmark());
+        if debug_level >= DEBUG_VERBOSE {
+            godot_print!(
+                "Kataru.run('{}'): {:#?}",
+                runner.bookmark().passage(),
+                runner.bookmark()
+            );
         }
+        self.record_history(id, &line, Cause::Run(passage), snapshot);
+        self.autosave_line(id, &line, passage_changed, false);
+        self.emit_line_signal(id, &line)?;
+        Ok(())
     }
-    fn try_run(&mut self, passage: String) -> Result<()> {
-        if let Some(runner) = self.runner.as_mut() {
-            let line = runner.run(passage)?;
-            if self.debug_level >= DEBUG_VERBOSE {
-                godot_print!(
-                    "Kataru.run('{}'): {:#?}",
-                    runner.bookmark().passage(),
-                    runner.bookmark()
-                );
-            }
-            self.emit_line_signal(&line);
-            Ok(())
-        } else {
-            godot_fatal!(self, "Kataru was not initialized before call to run.");
-            Err(error!("Kataru uninitialized."))
+
+    // Records a line produced by the runner named `id`, along with the bookmark from before it.
+    fn record_history(&mut self, id: &str, line: &Line, cause: Cause, snapshot: Bookmark) {
+        let passage = match self.bookmark(id) {
+            Ok(bookmark) => bookmark.passage().to_string(),
+            Err(_) => return,
+        };
+        let mut entry = HistoryEntry::new(line, cause, snapshot, &passage);
+        if let Line::Dialogue(dialogue) = line {
+            entry.text = self.translate(id, &dialogue.text);
         }
+        self.histories
+            .entry(id.to_string())
+            .or_default()
+            .push(entry);
+    }
+
+    /// Get the last `limit` lines emitted by the main runner, oldest first.
+    /// Each entry has `kind`, `passage`, `speaker`, `text`, `attributes`, `choices` and `chosen`.
+    /// A negative `limit` returns the whole history.
+    #[func]
+    pub fn get_history(&self, limit: i64) -> Array<Dictionary> {
+        self.get_history_on(MAIN_RUNNER.into(), limit)
+    }
+    /// Get the last `limit` lines emitted by the runner named `id`, oldest first.
+    #[func]
+    pub fn get_history_on(&self, id: GodotString, limit: i64) -> Array<Dictionary> {
+        match self.histories.get(&id.to_string()) {
+            Some(history) => history.to_array(limit),
+            None => Array::new(),
+        }
+    }
+
+    /// Forget every line emitted by the main runner.
+    #[func]
+    pub fn clear_history(&mut self) {
+        if let Some(history) = self.histories.get_mut(MAIN_RUNNER) {
+            history.clear();
+        }
+    }
+
+    /// Whether `save` and `load` also persist the main runner's history next to the bookmark.
+    #[func]
+    pub fn set_persist_history(&mut self, persist_history: bool) {
+        self.persist_history = persist_history;
+    }
+
+    /// Go back `steps` dialogue or choices lines on the main runner, restoring its bookmark
+    /// and re-emitting that line. Commands in between aren't run again.
+    #[func]
+    pub fn rewind(&mut self, steps: i64) -> ErrorKind {
+        self.rewind_on(MAIN_RUNNER.into(), steps)
+    }
+    /// Go back `steps` dialogue or choices lines on the runner named `id`, restoring its bookmark
+    /// and re-emitting that line.
+    #[func]
+    pub fn rewind_on(&mut self, id: GodotString, steps: i64) -> ErrorKind {
+        if self.debug_level >= DEBUG_INFO {
+            godot_print!("Kataru.rewind_on('{}', {})", id, steps);
+        }
+        let result = self.try_rewind(&id.to_string(), steps);
+        self.report(format!("Kataru.rewind_on('{}', {})", id, steps), result)
+    }
+    fn try_rewind(&mut self, id: &str, steps: i64) -> InterfaceResult<()> {
+        let Some((index, cause, bookmark)) = usize::try_from(steps)
+            .ok()
+            .filter(|&steps| steps >= 1)
+            .and_then(|steps| self.histories.get(id)?.replay_point(steps))
+        else {
+            return Err(KataruError::new(
+                ERROR_RUNTIME,
+                format!("Not enough lines in history to rewind {} lines.", steps),
+            ));
+        };
+
+        // Replay the line from the bookmark it was produced from. This goes straight to the
+        // runner so nothing is recorded, visited or autosaved again.
+        self.pending_commands.remove(id);
+        let runner = self.runner_mut(id)?;
+        runner.load_bookmark(bookmark).with_kind(ERROR_RUNTIME)?;
+        let line = match cause {
+            Cause::Next(input) => runner.next(&input),
+            Cause::Run(passage) => runner.run(passage),
+        }
+        .with_kind(ERROR_RUNTIME)?;
+        // Only forget the lines after it once it's been replayed.
+        if let Some(history) = self.histories.get_mut(id) {
+            history.rewind_to(index);
+        }
+        self.emit_line_signal(id, &line)
     }
 
     // Transforms a command name into the normalized version.
-    // This is used for lookups to command adapters in GDScript.
+    // This is used for lookups of the command's declared parameters.
     fn get_normalized_command(command: &str) -> String {
         // Get positions of character name.
         let mut colon: usize = 0;
@@ -280,174 +986,1030 @@ impl KataruInterface {
 
     /// Run the current passage until a choice is encountered.
     #[func]
-    pub fn run_until_choice(&mut self, passage: GodotString) {
+    pub fn run_until_choice(&mut self, passage: GodotString) -> ErrorKind {
+        self.run_until_choice_on(MAIN_RUNNER.into(), passage)
+    }
+    /// Run the current passage on the runner named `id` until a choice is encountered.
+    #[func]
+    pub fn run_until_choice_on(&mut self, id: GodotString, passage: GodotString) -> ErrorKind {
         if self.debug_level >= DEBUG_INFO {
-            godot_print!("Kataru.run_until_choice({})", passage);
-        }
-        if let Err(err) = self.try_run_until_choice(passage.to_string()) {
-            godot_error!("Kataru.run_until_choice({}): {}", passage, err)
+            godot_print!("Kataru.run_until_choice_on('{}', {})", id, passage);
         }
+        let result = self.try_run_until_choice(&id.to_string(), passage.to_string());
+        self.report(
+            format!("Kataru.run_until_choice_on('{}', {})", id, passage),
+            result,
+        )
     }
-    fn try_run_until_choice(&mut self, passage: String) -> Result<()> {
-        self.try_goto(passage)?;
+    fn try_run_until_choice(&mut self, id: &str, passage: String) -> InterfaceResult<()> {
+        self.try_goto(id, passage)?;
         loop {
-            let line = self.try_next("".to_string())?;
+            let line = self.try_next(id, "".to_string())?;
             match line {
                 Line::Choices(_) | Line::End => {
                     return Ok(());
                 }
                 _ => {}
             }
+            if self.pending_commands.contains_key(id) {
+                return Ok(());
+            }
         }
     }
 
-    pub fn get_state(&self, variable: GodotString) -> Variant {
-        match self.try_get_state(variable.to_string()) {
-            Ok(value) => val_to_variant(value),
+    /// Get the value of a state variable, or null if it does not exist.
+    #[func]
+    pub fn get_state(&mut self, variable: GodotString) -> Variant {
+        let result = self.try_get_state(variable.to_string()).map(val_to_variant);
+        match result {
+            Ok(value) => {
+                self.last_error = None;
+                value
+            }
             Err(err) => {
-                godot_error!("Kataru.get_state({}): {}", &variable, err);
+                self.report::<()>(format!("Kataru.get_state({})", variable), Err(err));
                 Variant::nil()
             }
         }
     }
-    fn try_get_state(&self, variable: String) -> Result<&Value> {
-        if let Some(runner) = &self.runner {
-            return runner.bookmark().value(&variable);
+    fn try_get_state(&self, variable: String) -> InterfaceResult<&Value> {
+        self.bookmark(MAIN_RUNNER)?
+            .value(&variable)
+            .with_kind(ERROR_VALIDATION)
+    }
+
+    /// Get every state variable as a dictionary.
+    /// Global variables are keyed by name, others by `namespace:name`.
+    #[func]
+    pub fn get_all_state(&mut self) -> Dictionary {
+        match self.try_get_all_state() {
+            Ok(state) => {
+                self.last_error = None;
+                state
+            }
+            Err(err) => {
+                self.report::<()>("Kataru.get_all_state()".to_string(), Err(err));
+                Dictionary::new()
+            }
         }
-        Err(error!("Kataru uninitialized."))
     }
+    fn try_get_all_state(&self) -> InterfaceResult<Dictionary> {
+        bookmark_state_to_dict(self.bookmark(MAIN_RUNNER)?).with_kind(ERROR_RUNTIME)
+    }
+
+    /// Set the value of a state variable.
     #[func]
-    pub fn set_state(&mut self, variable: GodotString, value: Variant) {
+    pub fn set_state(&mut self, variable: GodotString, value: Variant) -> ErrorKind {
         if self.debug_level >= DEBUG_INFO {
             godot_print!("Kataru.set_state({}, {})", variable, value);
         }
-        if let Err(err) = self.try_set_state(variable.to_string(), value) {
-            godot_error!("Kataru.run_until_choice({}): {}", &variable, err)
-        }
+        let result = self.try_set_state(variable.to_string(), value);
+        self.report(format!("Kataru.set_state({})", variable), result)
     }
-    pub fn try_set_state(&mut self, variable: String, variant: Variant) -> Result<()> {
-        let value = variant_to_val(variant)?;
-        if let Some(runner) = self.runner.as_mut() {
-            runner.set_state(
+    pub fn try_set_state(&mut self, variable: String, variant: Variant) -> InterfaceResult<()> {
+        let value = variant_to_val(&variant).with_kind(ERROR_VALIDATION)?;
+        self.runner_mut(MAIN_RUNNER)?
+            .set_state(
                 StateMod {
                     var: &variable,
                     op: AssignOperator::None,
                 },
                 value,
             )
-        } else {
-            godot_fatal!(
-                self,
-                "Kataru was not initialized before call to run_until_choice."
-            );
-            Err(error!("Kataru uninitialized."))
+            .with_kind(ERROR_VALIDATION)
+    }
+
+    /// Answer the main runner's input line, then run the next line.
+    /// `inputs` maps each requested state variable to its value.
+    #[func]
+    pub fn submit_input(&mut self, inputs: Dictionary) -> ErrorKind {
+        self.submit_input_on(MAIN_RUNNER.into(), inputs)
+    }
+    /// Answer the input line on the runner named `id`, then run the next line.
+    #[func]
+    pub fn submit_input_on(&mut self, id: GodotString, inputs: Dictionary) -> ErrorKind {
+        if self.debug_level >= DEBUG_INFO {
+            godot_print!("Kataru.submit_input_on('{}', {})", id, inputs);
         }
+        let result = self.try_submit_input(&id.to_string(), inputs);
+        self.report(format!("Kataru.submit_input_on('{}')", id), result)
     }
+    fn try_submit_input(&mut self, id: &str, inputs: Dictionary) -> InterfaceResult<()> {
+        self.check_not_waiting(id)?;
+        let Some(variables) = self.pending_inputs.get(id).cloned() else {
+            return Err(KataruError::new(
+                ERROR_RUNTIME,
+                format!("Runner '{}' is not waiting on input.", id),
+            ));
+        };
+        for (key, _) in inputs.iter_shared() {
+            let Ok(key) = key
+                .try_to::<String>()
+                .or_else(|_| key.try_to::<StringName>().map(|key| key.to_string()))
+            else {
+                return Err(KataruError::new(
+                    ERROR_VALIDATION,
+                    format!("Input keys must be strings, got {}.", key),
+                ));
+            };
+            if !variables.contains(&key) {
+                return Err(KataruError::new(
+                    ERROR_VALIDATION,
+                    format!("Input '{}' was not requested.", key),
+                ));
+            }
+        }
 
-    /// Exit the current dialogue passage.
+        // Check every field before writing any, so a bad submission leaves state untouched.
+        let runner = self.runner_mut(id)?;
+        let mut values = Vec::with_capacity(variables.len());
+        for variable in &variables {
+            let Some(variant) = inputs.get(variable.clone()) else {
+                return Err(KataruError::new(
+                    ERROR_VALIDATION,
+                    format!("Missing input '{}'.", variable),
+                ));
+            };
+            let value = variant_to_val(&variant).with_kind(ERROR_VALIDATION)?;
+            let declared = runner
+                .bookmark()
+                .value(variable)
+                .with_kind(ERROR_VALIDATION)?;
+            if value_type_name(&value) != value_type_name(declared) {
+                return Err(KataruError::new(
+                    ERROR_VALIDATION,
+                    format!(
+                        "Input '{}' should be a {} but was a {}.",
+                        variable,
+                        value_type_name(declared),
+                        value_type_name(&value)
+                    ),
+                ));
+            }
+            values.push((variable, value));
+        }
+        for (variable, value) in values {
+            runner
+                .set_state(
+                    StateMod {
+                        var: variable,
+                        op: AssignOperator::None,
+                    },
+                    value,
+                )
+                .with_kind(ERROR_VALIDATION)?;
+        }
+        self.try_next(id, String::new())?;
+        Ok(())
+    }
+
+    /// Save the main runner's bookmark to `path`.
+    #[func]
+    pub fn save(&mut self, path: GodotString) -> ErrorKind {
+        let result = self.try_save(path.to_string().into());
+        self.report(format!("Kataru.save('{}')", path), result)
+    }
+    fn try_save(&mut self, path: PathBuf) -> InterfaceResult<()> {
+        self.bookmark_path = path.clone();
+        let mut writes = AtomicWrites::default();
+        self.stage_save(&path, &mut writes)?;
+        writes.commit().with_kind(ERROR_IO)?;
+        self.try_save_read_lines()
+    }
+    // Stages the main runner's bookmark for `bookmark_path`, along with the files saved next to it.
+    // Nothing is replaced until `writes` is committed, so a save's files never end up out of step.
+    fn stage_save(
+        &mut self,
+        bookmark_path: &Path,
+        writes: &mut AtomicWrites,
+    ) -> InterfaceResult<()> {
+        let bookmark = self.bookmark(MAIN_RUNNER)?;
+        writes
+            .stage(bookmark_path, |path| bookmark.save(path))
+            .with_kind(ERROR_IO)?;
+        let version = SaveVersion {
+            story_hash: self.story_hash.clone(),
+        };
+        writes
+            .stage(&SaveVersion::path_for(bookmark_path), |path| {
+                version.save(path)
+            })
+            .with_kind(ERROR_IO)?;
+        if self.persist_history {
+            let history = self.histories.entry(MAIN_RUNNER.to_string()).or_default();
+            writes
+                .stage(&History::path_for(bookmark_path), |path| history.save(path))
+                .with_kind(ERROR_IO)?;
+        }
+        Ok(())
+    }
+
+    /// Set the directory that save slots are kept in.
+    #[func]
+    pub fn set_slots_path(&mut self, path: GodotString) {
+        self.slots_path = path.to_string().into();
+    }
+    // The directory save slots are kept in, which must be set before slots are used.
+    fn slots_path(&self) -> InterfaceResult<PathBuf> {
+        if self.slots_path.as_os_str().is_empty() {
+            return Err(KataruError::new(
+                ERROR_VALIDATION,
+                "No slots_path set for save slots.".to_string(),
+            ));
+        }
+        Ok(self.slots_path.clone())
+    }
+
+    /// Save the main runner to the slot named `name`, replacing it if it exists.
+    /// `metadata` is kept with the slot and returned by `list_slots`.
     #[func]
-    pub fn save(&mut self, path: GodotString) {
-        self.bookmark_path = path.to_string().into();
-        if let Some(runner) = &mut self.runner {
-            if let Err(err) = runner.bookmark().save(&self.bookmark_path) {
-                godot_error!("Kataru.save(): {}", err)
+    pub fn save_slot(&mut self, name: GodotString, metadata: Dictionary) -> ErrorKind {
+        if self.debug_level >= DEBUG_INFO {
+            godot_print!("Kataru.save_slot('{}')", name);
+        }
+        let result = self.try_save_slot(name.to_string(), metadata);
+        self.report(format!("Kataru.save_slot('{}')", name), result)
+    }
+    fn try_save_slot(&mut self, name: String, metadata: Dictionary) -> InterfaceResult<()> {
+        slots::validate_name(&name)?;
+        let slots_path = self.slots_path()?;
+        if let Err(err) = fs::create_dir_all(&slots_path) {
+            return Err(KataruError::new(
+                ERROR_IO,
+                format!(
+                    "Error creating save slot directory '{}': {}",
+                    slots_path.display(),
+                    err
+                ),
+            ));
+        }
+        let bookmark_path = slots::bookmark_path(&slots_path, &name);
+        let mut writes = AtomicWrites::default();
+        self.stage_save(&bookmark_path, &mut writes)?;
+
+        // The metadata is renamed last, so a new slot only shows up once the rest is saved.
+        let meta = SlotMeta {
+            name,
+            timestamp: slots::now(),
+            passage: self.bookmark(MAIN_RUNNER)?.passage().to_string(),
+            playtime: self.playtime,
+            metadata: variant_to_json(&Variant::from(metadata)),
+        };
+        writes
+            .stage(&SlotMeta::path_for(&bookmark_path), |path| meta.save(path))
+            .with_kind(ERROR_IO)?;
+        writes.commit().with_kind(ERROR_IO)?;
+        self.try_save_read_lines()
+    }
+
+    /// Returns every save slot, most recently saved first.
+    /// Each has its `name`, `timestamp` (seconds since the Unix epoch), `passage`, `playtime` and `metadata`.
+    #[func]
+    pub fn list_slots(&mut self) -> Array<Dictionary> {
+        let slots = self
+            .slots_path()
+            .and_then(|slots_path| slots::list(&slots_path).with_kind(ERROR_IO));
+        match slots {
+            Ok(slots) => {
+                self.last_error = None;
+                Array::from_iter(slots.iter().map(|slot| slot.to_dictionary()))
             }
+            Err(err) => {
+                self.report::<()>("Kataru.list_slots()".to_string(), Err(err));
+                Array::new()
+            }
+        }
+    }
+
+    /// Delete the save slot named `name`.
+    #[func]
+    pub fn delete_slot(&mut self, name: GodotString) -> ErrorKind {
+        let result = self.try_delete_slot(name.to_string());
+        self.report(format!("Kataru.delete_slot('{}')", name), result)
+    }
+    fn try_delete_slot(&mut self, name: String) -> InterfaceResult<()> {
+        slots::validate_name(&name)?;
+        if !slots::delete(&self.slots_path()?, &name).with_kind(ERROR_IO)? {
+            return Err(KataruError::new(
+                ERROR_IO,
+                format!("No save slot named '{}'.", name),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Load the save slot named `name` into the main runner.
+    #[func]
+    pub fn load_slot(&mut self, name: GodotString) -> ErrorKind {
+        if self.debug_level >= DEBUG_INFO {
+            godot_print!("Kataru.load_slot('{}')", name);
+        }
+        let result = self.try_load_slot(name.to_string());
+        self.report(format!("Kataru.load_slot('{}')", name), result)
+    }
+    fn try_load_slot(&mut self, name: String) -> InterfaceResult<()> {
+        slots::validate_name(&name)?;
+        let bookmark_path = slots::bookmark_path(&self.slots_path()?, &name);
+        let meta_path = SlotMeta::path_for(&bookmark_path);
+        if !meta_path.exists() {
+            return Err(KataruError::new(
+                ERROR_IO,
+                format!("No save slot named '{}'.", name),
+            ));
         }
+        let meta = SlotMeta::load(&meta_path).with_kind(ERROR_PARSE)?;
+        self.try_load(bookmark_path)?;
+        self.playtime = meta.playtime;
+        Ok(())
+    }
+
+    /// Autosave the main runner to rotating slots named `autosave-0`, `autosave-1`, ... in `slots_path`.
+    /// `triggers` are `AutosaveTrigger` flags: on passage changes, on choices taken, or every `every_lines` lines.
+    /// Once all `slots` autosaves are used, the oldest is replaced.
+    #[func]
+    pub fn set_autosave(&mut self, triggers: AutosaveTrigger, every_lines: i64, slots: i64) {
+        self.autosave.triggers = triggers;
+        self.autosave.every_lines = every_lines.max(0) as u32;
+        self.autosave.slots = slots.max(1) as u32;
     }
 
-    pub fn load(&mut self, path: GodotString) {
-        if let Err(err) = self.try_load(path.to_string().into()) {
-            godot_error!("Kataru.load(): {}", err);
+    // Autosaves the main runner if `line` sets off one of the autosave triggers.
+    fn autosave_line(&mut self, id: &str, line: &Line, passage_changed: bool, chose: bool) {
+        if id != MAIN_RUNNER || matches!(line, Line::InvalidChoice) {
+            return;
+        }
+        let dialogue = matches!(line, Line::Dialogue(_));
+        if self.autosave.on_line(dialogue, passage_changed, chose) {
+            self.autosave();
         }
     }
-    fn try_load(&mut self, path: PathBuf) -> Result<()> {
+    // Autosaves the main runner to the next autosave slot.
+    // Failures are reported without failing the call that produced the line.
+    fn autosave(&mut self) {
+        let slots_path = match self.slots_path() {
+            Ok(slots_path) => slots_path,
+            Err(err) => {
+                self.report::<()>("Kataru.autosave()".to_string(), Err(err));
+                return;
+            }
+        };
+        let name = self.autosave.next_slot(&slots_path);
+        if self.debug_level >= DEBUG_INFO {
+            godot_print!("Kataru.autosave('{}')", name);
+        }
+        let mut metadata = Dictionary::new();
+        metadata.insert("autosave", true);
+        let result = self.try_save_slot(name.clone(), metadata);
+        if result.is_err() {
+            self.report(format!("Kataru.autosave('{}')", name), result);
+        }
+    }
+
+    /// Seconds played, counted by `tick` and restored by `load_slot`.
+    #[func]
+    pub fn get_playtime(&self) -> f64 {
+        self.playtime
+    }
+
+    /// Load the bookmark at `path` into the main runner.
+    #[func]
+    pub fn load(&mut self, path: GodotString) -> ErrorKind {
+        let result = self.try_load(path.to_string().into());
+        self.report(format!("Kataru.load('{}')", path), result)
+    }
+    fn try_load(&mut self, path: PathBuf) -> InterfaceResult<()> {
         self.bookmark_path = path;
-        if let Some(runner) = self.runner.as_mut() {
-            runner.load_bookmark(Bookmark::load(&self.bookmark_path)?)
+        require_file(&self.bookmark_path)?;
+        let Some(story) = &self.story else {
+            return Err(KataruError::uninitialized());
+        };
+        let bookmark = Bookmark::load(&self.bookmark_path).with_kind(ERROR_PARSE)?;
+        let version = SaveVersion::load_for(&self.bookmark_path).with_kind(ERROR_PARSE)?;
+        let bookmark = self.migrate_bookmark(story, bookmark, version.map(|v| v.story_hash))?;
+        self.load_main_bookmark(bookmark)?;
+
+        // The old history doesn't lead up to the loaded bookmark, so replace it.
+        let history_path = History::path_for(&self.bookmark_path);
+        let history = if self.persist_history && history_path.exists() {
+            History::load(&history_path).with_kind(ERROR_IO)?
         } else {
-            Err(error!("Kataru was not initialized."))
+            History::default()
+        };
+        self.histories.insert(MAIN_RUNNER.to_string(), history);
+        Ok(())
+    }
+
+    // Loads `bookmark` into the main runner, dropping anything tied to its old position.
+    fn load_main_bookmark(&mut self, bookmark: Bookmark) -> InterfaceResult<()> {
+        self.runner_mut(MAIN_RUNNER)?
+            .load_bookmark(bookmark)
+            .with_kind(ERROR_RUNTIME)?;
+        self.choice_timeouts.remove(MAIN_RUNNER);
+        self.pending_inputs.remove(MAIN_RUNNER);
+        self.current_choices.remove(MAIN_RUNNER);
+        self.line_states.remove(MAIN_RUNNER);
+        Ok(())
+    }
+
+    /// Returns the main runner's bookmark as bytes, for saving somewhere other than a file.
+    /// Returns an empty array if it could not be serialized.
+    #[func]
+    pub fn snapshot(&mut self) -> PackedByteArray {
+        let source = self.snapshot_json();
+        PackedByteArray::from(source.to_string().as_bytes())
+    }
+
+    /// Returns the main runner's bookmark as a JSON string, or an empty string if it could not be serialized.
+    #[func]
+    pub fn snapshot_json(&mut self) -> GodotString {
+        let result = self
+            .bookmark(MAIN_RUNNER)
+            .and_then(|bookmark| snapshot::to_json(bookmark, &self.story_hash));
+        match result {
+            Ok(source) => {
+                self.last_error = None;
+                source.into()
+            }
+            Err(err) => {
+                self.report::<()>("Kataru.snapshot()".to_string(), Err(err));
+                GodotString::new()
+            }
         }
     }
 
+    /// Load a bookmark from bytes returned by `snapshot` into the main runner.
+    /// Corrupt snapshots are rejected.
+    #[func]
+    pub fn restore(&mut self, bytes: PackedByteArray) -> ErrorKind {
+        let result = match String::from_utf8(bytes.to_vec()) {
+            Ok(source) => self.try_restore(&source),
+            Err(_) => Err(KataruError::new(
+                ERROR_PARSE,
+                "Snapshot is not valid UTF-8, so it is corrupt.",
+            )),
+        };
+        self.report("Kataru.restore()".to_string(), result)
+    }
+
+    /// Load a bookmark from a string returned by `snapshot_json` into the main runner.
+    #[func]
+    pub fn restore_json(&mut self, source: GodotString) -> ErrorKind {
+        let result = self.try_restore(&source.to_string());
+        self.report("Kataru.restore_json()".to_string(), result)
+    }
+    fn try_restore(&mut self, source: &str) -> InterfaceResult<()> {
+        let Some(story) = &self.story else {
+            return Err(KataruError::uninitialized());
+        };
+        let (bookmark, saved_hash) = snapshot::from_json(source)?;
+        let bookmark = self.migrate_bookmark(story, bookmark, saved_hash)?;
+        self.load_main_bookmark(bookmark)?;
+        // The old history doesn't lead up to the restored bookmark.
+        self.histories
+            .insert(MAIN_RUNNER.to_string(), History::default());
+        Ok(())
+    }
+
     /// Exit the current dialogue passage.
     #[func]
     pub fn exit(&mut self) {
-        self.base.emit_signal(Self::END.into(), &[]);
+        self.base
+            .emit_signal(Self::END.into(), &[Variant::from(MAIN_RUNNER.to_string())]);
     }
 
     /// Emit a signal for the given line so GDScript can interact with it.
-    fn emit_line_signal(&mut self, line: &Line) {
+    /// Every signal carries the id of the runner that produced the line.
+    /// Commands are dispatched to their registered callable.
+    fn emit_line_signal(&mut self, id: &str, line: &Line) -> InterfaceResult<()> {
         if self.debug_level >= DEBUG_INFO {
-            godot_print!("Kataru.emit_line_signal({:#?})", line);
+            godot_print!("Kataru.emit_line_signal('{}', {:#?})", id, line);
         }
+        let runner_id = id.to_string();
+        let id = Variant::from(id.to_string());
+        let mut line_state = LineState::Blocking;
+        let mut dispatched = Ok(());
         match line {
-            Line::Dialogue(dialogue) => self.base.emit_signal(
-                Self::DIALOGUE.into(),
-                &[
-                    Variant::from(dialogue.name.to_string()),
-                    Variant::from(dialogue.text.to_string()),
-                    Variant::from(serde_to_json(&dialogue.attributes)),
-                ],
-            ),
-            Line::Choices(choices) => self.base.emit_signal(
-                Self::CHOICES.into(),
-                &[
-                    Variant::from(Array::<GodotString>::from_iter(
-                        choices.choices.iter().map(|e| e.into()),
-                    )),
-                    Variant::from(choices.timeout),
-                ],
-            ),
-            Line::Command(command) => self.base.emit_signal(
-                Self::COMMAND.into(),
-                &[
-                    Variant::from(command.name.to_string()),
-                    Variant::from(Self::get_normalized_command(&command.name)),
-                    serde_to_json(&command.params),
-                ],
-            ),
-            Line::Input(input_cmd) => self.base.emit_signal(
-                Self::INPUT_COMMAND.into(),
-                &[
-                    Variant::from(Dictionary::from(&input_cmd.input)),
-                    Variant::from(input_cmd.timeout),
-                ],
-            ),
-            Line::InvalidChoice => self.base.emit_signal(Self::INVALID_CHOICE.into(), &[]),
-            Line::End => self.base.emit_signal(Self::END.into(), &[]),
-        };
+            Line::Dialogue(dialogue) => {
+                let bookmark = self.bookmark(&runner_id)?;
+                let passage = bookmark.passage().to_string();
+                let line_id = lines::line_id(
+                    bookmark.namespace(),
+                    &passage,
+                    bookmark.line(),
+                    &dialogue.text,
+                );
+                let read_key = lines::line_key(bookmark.namespace(), &passage, bookmark.line());
+                line_state = if self.read_lines.insert(read_key) {
+                    LineState::Unread
+                } else {
+                    LineState::Read
+                };
+                let text = self.translate(&runner_id, &dialogue.text);
+                self.base.emit_signal(
+                    Self::DIALOGUE.into(),
+                    &[
+                        id,
+                        Variant::from(dialogue.name.to_string()),
+                        Variant::from(text),
+                        Variant::from(attributes_to_array(&dialogue.attributes)),
+                        Variant::from(line_id),
+                        Variant::from(passage),
+                    ],
+                );
+            }
+            Line::Choices(choices) => {
+                let Some(story) = &self.story else {
+                    return Err(KataruError::uninitialized());
+                };
+                let mut infos = describe_choices(story, self.bookmark(&runner_id)?, choices);
+                for info in &mut infos {
+                    info.text = self.translate(&runner_id, &info.id);
+                }
+                let payload =
+                    Array::<Dictionary>::from_iter(infos.iter().map(|c| c.to_dictionary()));
+                self.current_choices.insert(runner_id.clone(), infos);
+                self.base.emit_signal(
+                    Self::CHOICES.into(),
+                    &[id, Variant::from(payload), Variant::from(choices.timeout)],
+                );
+            }
+            Line::Command(command) => {
+                line_state = LineState::Passable;
+                // Finish updating the runner's state before reporting a failed command.
+                dispatched = self.dispatch_command(&runner_id, command);
+            }
+            Line::Input(input_cmd) => {
+                self.base.emit_signal(
+                    Self::INPUT_COMMAND.into(),
+                    &[
+                        id,
+                        Variant::from(Dictionary::from(&input_cmd.input)),
+                        Variant::from(input_cmd.timeout),
+                    ],
+                );
+            }
+            Line::InvalidChoice => {
+                self.base.emit_signal(Self::INVALID_CHOICE.into(), &[id]);
+            }
+            Line::End => {
+                self.base.emit_signal(Self::END.into(), &[id]);
+            }
+        }
+        self.line_states.insert(runner_id.clone(), line_state);
+        // An invalid choice leaves the runner on the same choices, so keep them and their deadline.
+        if !matches!(line, Line::InvalidChoice) {
+            self.choice_timeouts.remove(&runner_id);
+            self.pending_inputs.remove(&runner_id);
+            if !matches!(line, Line::Choices(_)) {
+                self.current_choices.remove(&runner_id);
+            }
+        }
+        match line {
+            Line::Choices(choices) => {
+                if let Some(timeout) = ChoiceTimeout::new(choices) {
+                    self.choice_timeouts.insert(runner_id.clone(), timeout);
+                }
+            }
+            Line::Input(input_cmd) => {
+                let variables = input_cmd.input.keys().cloned().collect();
+                self.pending_inputs.insert(runner_id.clone(), variables);
+            }
+            _ => {}
+        }
+        if let Some(autoplay) = self.autoplay.get_mut(&runner_id) {
+            autoplay.schedule(line, &self.autoplay_settings);
+        }
+        dispatched
     }
 
-    fn watch_dir_changed(&mut self) -> bool {
-        if let Some(watch_dir) = &self.watch_dir {
-            let old_modified_time = self.modified_time;
-            self.modified_time = last_modified_time(watch_dir);
-            match (self.modified_time, old_modified_time) {
-                (Some(current), Some(previous)) => current != previous,
-                (Some(_), None) => true,
-                _ => false,
+    // Emits the `command` signal for a command run by the runner named `id`, with its parameters
+    // filled in from their declared defaults, and calls the function registered for it.
+    fn dispatch_command(&mut self, id: &str, command: &Command) -> InterfaceResult<()> {
+        let normalized_name = Self::get_normalized_command(&command.name);
+        let params = self.commands.resolve(&normalized_name, &command.params)?;
+        let args = [
+            Variant::from(id.to_string()),
+            Variant::from(command.name.to_string()),
+            Variant::from(normalized_name.clone()),
+            Variant::from(params_to_dict(&params)),
+        ];
+        self.base.emit_signal(Self::COMMAND.into(), &args);
+        match self
+            .commands
+            .dispatch(&command.name, &normalized_name, &params)?
+        {
+            // Hold the runner until the command completes.
+            Some(result) if commands::is_pending(&result) => {
+                self.pending_commands
+                    .entry(id.to_string())
+                    .or_default()
+                    .count += 1;
+                self.base
+                    .emit_signal(Self::COMMAND_PENDING.into(), &[args[0].clone(), result]);
+            }
+            Some(_) => {}
+            None => {
+                self.base.emit_signal(Self::UNHANDLED_COMMAND.into(), &args);
+                if self.strict_commands {
+                    return Err(KataruError::new(
+                        ERROR_RUNTIME,
+                        format!("No function registered for command '{}'.", command.name),
+                    ));
+                }
             }
-        } else {
-            false
         }
+        Ok(())
     }
 
+    /// Advance timers by `delta` seconds. Call this every frame from `_process`.
+    /// Auto-playing runners move to their next line once it's been shown long enough,
+    /// and timed choices submit their default once their deadline passes.
     #[func]
-    fn watch_story_dir(&mut self, delta: f64) {
-        // File watcher
-        self.watch_poll_time += delta;
-        if self.watch_poll_time < self.watch_poll_interval {
-            return;
+    pub fn tick(&mut self, delta: f64) {
+        self.playtime += delta;
+        let mut timed_out = Vec::new();
+        for (id, timeout) in &mut self.choice_timeouts {
+            if self.pending_commands.contains_key(id) {
+                continue;
+            }
+            if let Some(choice) = timeout.tick(delta) {
+                timed_out.push((id.clone(), choice));
+            }
         }
-        self.watch_poll_time -= self.watch_poll_interval;
-        if self.watch_dir_changed() {
+        for (id, choice) in timed_out {
+            self.choice_timeouts.remove(&id);
             if self.debug_level >= DEBUG_INFO {
-                godot_print!("Kataru story directory changed.")
+                godot_print!(
+                    "Kataru.tick: choices on '{}' timed out, choosing '{}'",
+                    id,
+                    choice
+                );
+            }
+            self.base.emit_signal(
+                Self::CHOICE_TIMEOUT.into(),
+                &[Variant::from(id.clone()), Variant::from(choice.clone())],
+            );
+            let result = self.try_next(&id, choice);
+            self.report(format!("Kataru.tick('{}')", id), result);
+        }
+
+        let mut advances = Vec::new();
+        for (id, autoplay) in &mut self.autoplay {
+            // Pending commands hold the runner, so don't count down until they're done.
+            if self.pending_commands.contains_key(id) {
+                continue;
+            }
+            if autoplay.tick(delta) {
+                advances.push(id.clone());
+            }
+        }
+        for id in advances {
+            let result = self.try_next(&id, "".to_string());
+            self.report(format!("Kataru.tick('{}')", id), result);
+        }
+
+        let skipping: Vec<String> = self
+            .skip_modes
+            .keys()
+            .filter(|id| self.can_skip(id))
+            .cloned()
+            .collect();
+        for id in skipping {
+            let result = self.try_skip(&id);
+            self.report(format!("Kataru.tick('{}')", id), result);
+        }
+    }
+
+    /// Set which lines the runner named `id` skips past: `SKIP_OFF`, `SKIP_READ` or `SKIP_ALL`.
+    /// Skipping stops at unread lines (unless skipping all), choices, inputs and the end,
+    /// and carries on once the player moves past them.
+    #[func]
+    pub fn set_skip_mode(&mut self, id: GodotString, mode: SkipMode) -> ErrorKind {
+        if self.debug_level >= DEBUG_INFO {
+            godot_print!("Kataru.set_skip_mode('{}', {})", id, mode);
+        }
+        let result = self.try_set_skip_mode(&id.to_string(), mode);
+        self.report(format!("Kataru.set_skip_mode('{}', {})", id, mode), result)
+    }
+    fn try_set_skip_mode(&mut self, id: &str, mode: SkipMode) -> InterfaceResult<()> {
+        if mode > SKIP_ALL {
+            return Err(KataruError::new(
+                ERROR_VALIDATION,
+                format!("Invalid skip mode {}.", mode),
+            ));
+        }
+        self.bookmark(id)?;
+        if mode == SKIP_OFF {
+            self.skip_modes.remove(id);
+            return Ok(());
+        }
+        self.skip_modes.insert(id.to_string(), mode);
+        self.try_skip(id)
+    }
+
+    #[func]
+    pub fn get_skip_mode(&self, id: GodotString) -> SkipMode {
+        self.skip_modes
+            .get(&id.to_string())
+            .copied()
+            .unwrap_or(SKIP_OFF)
+    }
+
+    // Returns true if the runner named `id` can skip past its current line.
+    fn can_skip(&self, id: &str) -> bool {
+        let mode = self.skip_modes.get(id).copied().unwrap_or(SKIP_OFF);
+        !self.pending_commands.contains_key(id)
+            && self
+                .line_states
+                .get(id)
+                .is_some_and(|state| state.skippable(mode))
+    }
+
+    // Runs lines on the runner named `id` for as long as its skip mode allows.
+    fn try_skip(&mut self, id: &str) -> InterfaceResult<()> {
+        self.autosave.suspended = true;
+        let mut result = Ok(());
+        for _ in 0..MAX_SKIPPED_LINES {
+            if !self.can_skip(id) {
+                break;
             }
-            if let Err(err) = self.try_init() {
-                godot_fatal!(self, "Kataru.init(): {}", err);
+            if let Err(err) = self.try_next(id, "".to_string()) {
+                result = Err(err);
+                break;
             }
         }
+        // Save once where skipping stopped, rather than at every line skipped past.
+        if self.autosave.resume() {
+            self.autosave();
+        }
+        result
+    }
+
+    /// Set the file that read lines are stored in, independent of any save.
+    /// Must be called before `init`.
+    #[func]
+    pub fn set_read_lines_path(&mut self, path: GodotString) {
+        self.read_lines_path = path.to_string().into();
+    }
+
+    /// Write the lines the player has read to the read lines file.
+    #[func]
+    pub fn save_read_lines(&mut self) -> ErrorKind {
+        let result = self.try_save_read_lines();
+        self.report("Kataru.save_read_lines()".to_string(), result)
+    }
+    fn try_save_read_lines(&self) -> InterfaceResult<()> {
+        if self.read_lines_path.as_os_str().is_empty() {
+            return Ok(());
+        }
+        write_atomic(&self.read_lines_path, |path| self.read_lines.save(path)).with_kind(ERROR_IO)
+    }
+
+    /// Set the choice picked when the timed choices on the runner named `id` run out of time.
+    /// Applies to the current choices only; by default the first choice is picked.
+    #[func]
+    pub fn set_timeout_choice(&mut self, id: GodotString, choice: GodotString) -> ErrorKind {
+        let result = self.try_set_timeout_choice(&id.to_string(), choice.to_string());
+        self.report(
+            format!("Kataru.set_timeout_choice('{}', '{}')", id, choice),
+            result,
+        )
+    }
+    fn try_set_timeout_choice(&mut self, id: &str, choice: String) -> InterfaceResult<()> {
+        let Some(timeout) = self.choice_timeouts.get_mut(id) else {
+            return Err(KataruError::new(
+                ERROR_RUNTIME,
+                format!("Runner '{}' is not waiting on timed choices.", id),
+            ));
+        };
+        if !timeout.set_default(choice.clone()) {
+            return Err(KataruError::new(
+                ERROR_VALIDATION,
+                format!("'{}' is not one of the current choices.", choice),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the seconds left on the timed choices of the runner named `id`, or -1 if there are none.
+    #[func]
+    pub fn get_choice_time_left(&self, id: GodotString) -> f64 {
+        match self.choice_timeouts.get(&id.to_string()) {
+            Some(timeout) => timeout.remaining.max(0.0),
+            None => -1.0,
+        }
+    }
+
+    /// Turn auto-play on or off for the runner named `id`.
+    #[func]
+    pub fn set_autoplay(&mut self, id: GodotString, enabled: bool) {
+        if self.debug_level >= DEBUG_INFO {
+            godot_print!("Kataru.set_autoplay('{}', {})", id, enabled);
+        }
+        let id = id.to_string();
+        if !enabled {
+            self.autoplay.remove(&id);
+            return;
+        }
+        let mut autoplay = Autoplay::default();
+        // Time the line currently on screen.
+        if let Some(entry) = self.histories.get(&id).and_then(|history| history.last()) {
+            autoplay.schedule_entry(&entry.kind, &entry.text, &self.autoplay_settings);
+        }
+        self.autoplay.insert(id, autoplay);
+    }
+
+    /// Pause or resume auto-play for the runner named `id`, keeping the current line's timer.
+    #[func]
+    pub fn set_autoplay_paused(&mut self, id: GodotString, paused: bool) {
+        if let Some(autoplay) = self.autoplay.get_mut(&id.to_string()) {
+            autoplay.paused = paused;
+        }
+    }
+
+    /// Returns true if auto-play is on and not paused for the runner named `id`.
+    #[func]
+    pub fn is_autoplaying(&self, id: GodotString) -> bool {
+        match self.autoplay.get(&id.to_string()) {
+            Some(autoplay) => !autoplay.paused,
+            None => false,
+        }
+    }
+
+    /// Skip the rest of the current line's time on an auto-playing runner.
+    /// Returns false if it's waiting on the player instead.
+    #[func]
+    pub fn skip_line(&mut self, id: GodotString) -> bool {
+        match self.autoplay.get_mut(&id.to_string()) {
+            Some(autoplay) => autoplay.skip(),
+            None => false,
+        }
+    }
+
+    /// Set how long auto-play shows each line: its length at `chars_per_second`,
+    /// but at least `min_line_time` seconds.
+    #[func]
+    pub fn set_autoplay_timing(&mut self, chars_per_second: f64, min_line_time: f64) {
+        self.autoplay_settings = AutoplaySettings {
+            chars_per_second,
+            min_line_time,
+        };
+    }
+
+    /// Register `callable` to handle the command `name`.
+    /// For a `$character` command, pass `character` to handle it only for that character.
+    /// The callable receives the command's parameters in declared order, with defaults filled in.
+    #[func]
+    pub fn register_command(
+        &mut self,
+        name: GodotString,
+        callable: Callable,
+        character: GodotString,
+    ) {
+        if self.debug_level >= DEBUG_INFO {
+            godot_print!("Kataru.register_command('{}', '{}')", name, character);
+        }
+        self.commands
+            .register(&name.to_string(), callable, &character.to_string());
+    }
+
+    /// Remove the callable registered for the command `name` (and `character`, if given).
+    /// Returns false if nothing was registered.
+    #[func]
+    pub fn unregister_command(&mut self, name: GodotString, character: GodotString) -> bool {
+        self.commands
+            .unregister(&name.to_string(), &character.to_string())
+    }
+
+    /// Marks one of the runner's pending commands as complete.
+    /// Once none are left, runs a `next` call made while waiting, or advances on its own
+    /// if `advance_after_commands` is set.
+    #[func]
+    pub fn complete_command(&mut self, id: GodotString) -> ErrorKind {
+        if self.debug_level >= DEBUG_INFO {
+            godot_print!("Kataru.complete_command('{}')", id);
+        }
+        let result = self.try_complete_command(&id.to_string());
+        self.report(format!("Kataru.complete_command('{}')", id), result)
+    }
+    fn try_complete_command(&mut self, id: &str) -> InterfaceResult<()> {
+        let Some(pending) = self.pending_commands.get_mut(id) else {
+            return Err(KataruError::new(
+                ERROR_RUNTIME,
+                format!("Runner '{}' has no pending commands.", id),
+            ));
+        };
+        pending.count -= 1;
+        if pending.count > 0 {
+            return Ok(());
+        }
+        let deferred_input = pending.deferred_input.take();
+        self.pending_commands.remove(id);
+        match deferred_input {
+            Some(input) => self.try_next(id, input).map(|_| ()),
+            None if self.advance_after_commands => self.try_next(id, "".to_string()).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns true if the runner named `id` is waiting on pending commands.
+    #[func]
+    pub fn is_waiting(&self, id: GodotString) -> bool {
+        self.pending_commands.contains_key(&id.to_string())
+    }
+
+    // Pending commands hold the runner named `id`: nothing moves it until they complete.
+    fn check_not_waiting(&self, id: &str) -> InterfaceResult<()> {
+        match self.pending_commands.get(id) {
+            Some(pending) => Err(KataruError::new(
+                ERROR_RUNTIME,
+                format!(
+                    "Runner '{}' is waiting on {} pending command(s).",
+                    id, pending.count
+                ),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether runners advance on their own once their pending commands complete.
+    #[func]
+    pub fn set_advance_after_commands(&mut self, advance_after_commands: bool) {
+        self.advance_after_commands = advance_after_commands;
+    }
+
+    /// The value a command's callable returns to hold the dialogue until `complete_command`.
+    #[func]
+    pub fn pending_marker(&self) -> GodotString {
+        PENDING_COMMAND.into()
+    }
+
+    /// Whether commands without a registered callable are reported as errors.
+    #[func]
+    pub fn set_strict_commands(&mut self, strict_commands: bool) {
+        self.strict_commands = strict_commands;
+    }
+
+    /// Recompile the story if its source files changed.
+    /// File events are collected off the main thread, so this is cheap to call every frame.
+    #[func]
+    fn watch_story_dir(&mut self, _delta: f64) {
+        let Some(watcher) = self.watcher.as_mut() else {
+            return;
+        };
+        let debounce = Duration::from_secs_f64(self.watch_poll_interval.max(0.0));
+        let changes = match watcher.poll(debounce) {
+            Ok(Some(changes)) => changes,
+            Ok(None) => return,
+            Err(err) => {
+                self.report::<()>(
+                    "Kataru.watch_story_dir()".to_string(),
+                    Err(KataruError::new(ERROR_IO, err.to_string())),
+                );
+                return;
+            }
+        };
+        if self.debug_level >= DEBUG_INFO {
+            for (path, kind) in &changes {
+                godot_print!("Kataru story file {}: {}", kind, path.display());
+            }
+        }
+
+        // Only reload in place once there's a live story to carry over.
+        if self.story.is_none() {
+            let result = self.try_init();
+            self.report_init(result);
+            return;
+        }
+        match self.try_reload() {
+            Ok(position_preserved) => {
+                self.last_error = None;
+                let changed_files = PackedStringArray::from_iter(
+                    changes
+                        .iter()
+                        .map(|(path, _kind)| Self::display_path(&self.story_src_path, path)),
+                );
+                self.base.emit_signal(
+                    Self::RELOADED.into(),
+                    &[
+                        Variant::from(changed_files),
+                        Variant::from(position_preserved),
+                    ],
+                );
+            }
+            Err(err) => {
+                self.report::<()>("Kataru.reload()".to_string(), Err(err));
+            }
+        }
+    }
+
+    // Path of `path` relative to `root`, for reporting to GDScript.
+    fn display_path(root: &Path, path: &Path) -> GodotString {
+        path.strip_prefix(root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string()
+            .into()
     }
 
     #[signal]
@@ -455,30 +2017,67 @@ impl KataruInterface {
     const LOADED: &str = "loaded";
 
     #[signal]
-    fn dialogue(char_name: GodotString, text: GodotString, attributes: GodotString);
+    fn reloaded(changed_files: PackedStringArray, position_preserved: bool);
+    const RELOADED: &str = "reloaded";
+
+    #[signal]
+    fn dialogue(
+        runner_id: GodotString,
+        char_name: GodotString,
+        text: GodotString,
+        attributes: Array<Variant>,
+        line_id: GodotString,
+        passage: GodotString,
+    );
     const DIALOGUE: &str = "dialogue";
 
     #[signal]
-    fn choices(choices: Array<GodotString>, timeout: f64);
+    fn choices(runner_id: GodotString, choices: Array<Dictionary>, timeout: f64);
     const CHOICES: &str = "choices";
 
     #[signal]
-    fn command(cmd_name: GodotString, params: GodotString);
+    fn choice_timeout(runner_id: GodotString, choice: GodotString);
+    const CHOICE_TIMEOUT: &str = "choice_timeout";
+
+    #[signal]
+    fn command(
+        runner_id: GodotString,
+        cmd_name: GodotString,
+        normalized_name: GodotString,
+        params: Dictionary,
+    );
     const COMMAND: &str = "command";
 
     #[signal]
-    fn input_command(inputs: Dictionary, timeout: f64);
+    fn unhandled_command(
+        runner_id: GodotString,
+        cmd_name: GodotString,
+        normalized_name: GodotString,
+        params: Dictionary,
+    );
+    const UNHANDLED_COMMAND: &str = "unhandled_command";
+
+    #[signal]
+    fn command_pending(runner_id: GodotString, awaitable: Variant);
+    const COMMAND_PENDING: &str = "command_pending";
+
+    #[signal]
+    fn input_command(runner_id: GodotString, inputs: Dictionary, timeout: f64);
     const INPUT_COMMAND: &str = "input_command";
 
     #[signal]
-    fn invalid_choice();
+    fn invalid_choice(runner_id: GodotString);
     const INVALID_CHOICE: &str = "invalid_choice";
 
     #[signal]
-    fn end();
+    fn end(runner_id: GodotString);
     const END: &str = "end";
 
     #[signal]
     fn fatal(message: GodotString);
     const FATAL: &str = "fatal";
+
+    #[signal]
+    fn error(kind: i64, message: GodotString, context: GodotString);
+    const ERROR: &str = "error";
 }
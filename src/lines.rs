@@ -0,0 +1,82 @@
+//! Stable ids for lines, for keying voice-over, analytics and translations to a specific line.
+//!
+//! A line's id is derived from where it is and what it says: `namespace:passage:line:hash`.
+//! Ids can also be pinned in the story by ending a line with `[#id]`, which is stripped from the emitted text.
+//! The same id keys the line's translations.
+
+/// 32-bit FNV-1a, which unlike `DefaultHasher` is stable across Rust versions.
+pub fn content_hash(text: &str) -> u32 {
+    let mut hash: u32 = 0x811c9dc5;
+    for byte in text.bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    hash
+}
+
+/// Splits a trailing `[#id]` off `text`, returning the remaining text and the pinned id.
+pub fn split_pinned_id(text: &str) -> (&str, Option<&str>) {
+    let trimmed = text.trim_end();
+    if let Some(rest) = trimmed.strip_suffix(']') {
+        if let Some(start) = rest.rfind("[#") {
+            let id = &rest[start + 2..];
+            if !id.is_empty() && !id.contains(char::is_whitespace) {
+                return (rest[..start].trim_end(), Some(id));
+            }
+        }
+    }
+    (text, None)
+}
+
+/// The position of the line at index `line` of `passage`, relative to `namespace`.
+pub fn line_key(namespace: &str, passage: &str, line: usize) -> String {
+    let (namespace, passage) = passage.split_once(':').unwrap_or((namespace, passage));
+    format!("{}:{}:{}", namespace, passage, line)
+}
+
+/// The id of a line saying `text` at index `line` of `passage`, relative to `namespace`:
+/// the id pinned to it, or one made from its position and a hash of its text.
+pub fn line_id(namespace: &str, passage: &str, line: usize, text: &str) -> String {
+    match split_pinned_id(text) {
+        (_, Some(pinned_id)) => pinned_id.to_string(),
+        (text, None) => format!(
+            "{}:{:08x}",
+            line_key(namespace, passage, line),
+            content_hash(text)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinned_ids_are_split_off() {
+        assert_eq!(
+            split_pinned_id("Welcome! [#may_welcome]"),
+            ("Welcome!", Some("may_welcome"))
+        );
+        assert_eq!(split_pinned_id("Welcome![#a] "), ("Welcome!", Some("a")));
+        assert_eq!(split_pinned_id("Welcome!"), ("Welcome!", None));
+        assert_eq!(split_pinned_id("Welcome! [#]"), ("Welcome! [#]", None));
+        assert_eq!(split_pinned_id("Two [#a b]"), ("Two [#a b]", None));
+    }
+
+    #[test]
+    fn line_ids_include_position_and_hash() {
+        let id = line_id("global", "Start", 3, "Hello!");
+        assert_eq!(id, format!("global:Start:3:{:08x}", content_hash("Hello!")));
+        assert_ne!(id, line_id("global", "Start", 4, "Hello!"));
+        assert_ne!(id, line_id("global", "Start", 3, "Hello?"));
+        assert_eq!(
+            line_id("global", "town:Square", 1, "Hi"),
+            line_id("town", "Square", 1, "Hi")
+        );
+    }
+
+    #[test]
+    fn pinned_ids_replace_line_ids() {
+        assert_eq!(line_id("global", "Start", 3, "Hello! [#greet]"), "greet");
+    }
+}
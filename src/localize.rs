@@ -0,0 +1,497 @@
+//! String tables for translating dialogue and choices.
+//!
+//! Strings are extracted from the story into a CSV file in the layout Godot's translation importer expects:
+//! a `keys` column followed by one column per locale. A `.pot` template is written next to it for PO-based workflows.
+//! Each string is keyed by the `line_id` of the line saying it: its namespace, passage, line and a hash of its text,
+//! or the id pinned to it with `[#id]`.
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use kataru::*;
+
+use crate::choices::flatten_choices;
+use crate::lines::{line_id, split_pinned_id};
+use crate::KataruInterface;
+
+/// A translatable string from the story.
+pub struct SourceString {
+    pub id: String,
+    pub text: String,
+    /// The character saying the line, `choice` for choice labels, or empty for narration.
+    pub context: String,
+}
+
+// Removes attribute tags like `<b>` and `</b>`, which runners strip from the text they emit.
+fn strip_attributes(text: &str, attributes: &HashSet<String>) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        stripped.push_str(&rest[..start]);
+        let tag = &rest[start..];
+        let name = tag[1..]
+            .split('>')
+            .next()
+            .map(|name| name.trim_start_matches('/').trim_end_matches('/'));
+        match (tag.find('>'), name) {
+            (Some(end), Some(name)) if attributes.contains(name) => rest = &tag[end + 1..],
+            _ => {
+                stripped.push('<');
+                rest = &tag[1..];
+            }
+        }
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+// Whether a serialized line is an `if`/`elif`/`else` block.
+fn is_branches(object: &serde_yaml::Mapping) -> bool {
+    !object.is_empty()
+        && object.keys().all(|key| {
+            key.as_str().is_some_and(|key| {
+                key.starts_with("if ") || key.starts_with("elif ") || key.starts_with("else")
+            })
+        })
+}
+
+// Collects the strings in serialized `lines`, which start at line `start` of their passage,
+// along with the line a runner is on when it emits each one. Lines are counted the way the runner
+// flattens a passage: branches and embedded choice passages are inlined, separated by a break line.
+// Returns the line after the last one. YAML values are walked since, unlike JSON ones, they keep
+// the story's order.
+fn collect_strings(
+    lines: &[serde_yaml::Value],
+    start: usize,
+    characters: &HashSet<String>,
+    is_command: &impl Fn(&str) -> bool,
+    found: &mut Vec<(String, String, usize)>,
+) -> usize {
+    let mut line = start;
+    for value in lines {
+        // Choices are emitted on their own line; dialogue after the runner moves past it.
+        let at = line;
+        line += 1;
+        let serde_yaml::Value::Mapping(object) = value else {
+            if let serde_yaml::Value::String(text) = value {
+                found.push((String::new(), text.clone(), line));
+            }
+            continue;
+        };
+        if let Some(block @ serde_yaml::Value::Mapping(choices)) = object.get("choices") {
+            if let Ok(serde_json::Value::Object(block)) = serde_json::to_value(block) {
+                for (text, _target, _condition) in flatten_choices(&block) {
+                    found.push(("choice".to_string(), text, at));
+                }
+            }
+            let mut embedded = false;
+            for choice in choices.values() {
+                let targets: Vec<&serde_yaml::Value> = match choice {
+                    serde_yaml::Value::Mapping(group) => group.values().collect(),
+                    target => vec![target],
+                };
+                for target in targets {
+                    if let serde_yaml::Value::Sequence(target_lines) = target {
+                        line = collect_strings(target_lines, line, characters, is_command, found);
+                        line += 1;
+                        embedded = true;
+                    }
+                }
+            }
+            // The last embedded passage isn't followed by a break.
+            if embedded {
+                line -= 1;
+            }
+            if let Some(serde_yaml::Value::Sequence(default_lines)) = object.get("default") {
+                line = collect_strings(default_lines, line, characters, is_command, found);
+            }
+        } else if is_branches(object) {
+            for branch_lines in object.values() {
+                if let serde_yaml::Value::Sequence(branch_lines) = branch_lines {
+                    line = collect_strings(branch_lines, line, characters, is_command, found);
+                }
+                line += 1;
+            }
+            // The last branch isn't followed by a break.
+            line -= 1;
+        } else {
+            for (key, value) in object {
+                if let (Some(key), serde_yaml::Value::String(text)) = (key.as_str(), value) {
+                    if characters.contains(key) && !is_command(key) {
+                        found.push((key.to_string(), text.clone(), line));
+                    }
+                }
+            }
+        }
+    }
+    line
+}
+
+/// Extracts every dialogue line and choice label in `story`, in a stable order.
+pub fn extract_strings(story: &Story) -> Vec<SourceString> {
+    let mut characters = HashSet::new();
+    let mut attributes = HashSet::new();
+    let mut commands = HashSet::new();
+    for (namespace, section) in &story.sections {
+        for (character, _character_data) in &section.config.characters {
+            characters.insert(character.to_string());
+            characters.insert(format!("{}:{}", namespace, character));
+        }
+        for (attribute, _attribute_data) in &section.config.attributes {
+            attributes.insert(attribute.to_string());
+        }
+        for (command, _params) in &section.config.commands {
+            commands.insert(command.to_string());
+            commands.insert(format!("{}:{}", namespace, command));
+        }
+    }
+    // Command parameters aren't dialogue. Besides declared commands, `Character.command` is
+    // always a character command.
+    let is_command = |key: &str| {
+        let normalized = KataruInterface::get_normalized_command(key);
+        commands.contains(key)
+            || commands.contains(&normalized)
+            || key
+                .rsplit_once('.')
+                .is_some_and(|(character, _)| characters.contains(character))
+    };
+
+    let sections: BTreeMap<_, _> = story.sections.iter().collect();
+    let mut strings = Vec::new();
+    let mut ids = HashSet::new();
+    for (namespace, section) in sections {
+        let passages: BTreeMap<_, _> = section.passages.iter().collect();
+        for (passage_name, passage) in passages {
+            let Ok(serde_yaml::Value::Sequence(passage)) = serde_yaml::to_value(passage) else {
+                continue;
+            };
+            let mut found = Vec::new();
+            collect_strings(&passage, 0, &characters, &is_command, &mut found);
+            for (context, text, line) in found {
+                let text = strip_attributes(&text, &attributes);
+                let id = line_id(namespace, passage_name, line, &text);
+                let text = split_pinned_id(&text).0.to_string();
+                // A pinned id keys one string, however many lines use it.
+                if ids.insert(id.clone()) {
+                    strings.push(SourceString { id, text, context });
+                }
+            }
+        }
+    }
+    strings
+}
+
+/// Translations already in a string table, by key and then locale column.
+/// They're kept when strings are extracted again; at runtime Godot's imported translations are used.
+#[derive(Default)]
+pub struct StringTable {
+    locales: Vec<String>,
+    rows: HashMap<String, Vec<String>>,
+}
+
+impl StringTable {
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) => {
+                return Err(error!(
+                    "Error reading string table from '{}': {}",
+                    path.display(),
+                    err
+                ))
+            }
+        };
+        Ok(Self::parse(&source))
+    }
+
+    pub fn parse(source: &str) -> Self {
+        let mut records = parse_csv(source).into_iter();
+        let Some(header) = records.next() else {
+            return Self::default();
+        };
+        let locales = header.into_iter().skip(1).collect();
+        let rows = records
+            .filter(|record| !record.iter().all(|field| field.is_empty()))
+            .map(|mut record| {
+                let id = record.remove(0);
+                (id, record)
+            })
+            .collect();
+        Self { locales, rows }
+    }
+
+    fn get(&self, id: &str, locale: &str) -> Option<&str> {
+        let column = self.locales.iter().position(|l| l == locale)?;
+        let text = self.rows.get(id)?.get(column)?;
+        if text.is_empty() {
+            None
+        } else {
+            Some(text)
+        }
+    }
+}
+
+/// Writes the strings in `story` to the table at `path` and a `.pot` template next to it.
+/// Translations already in the table are kept for strings that still exist.
+pub fn try_export_strings(path: &Path, story: &Story, source_locale: &str) -> Result<()> {
+    let strings = extract_strings(story);
+    let existing = if path.exists() {
+        StringTable::load(path)?
+    } else {
+        StringTable::default()
+    };
+    write_if_changed(path, format_table(&strings, &existing, source_locale))?;
+    write_if_changed(&path.with_extension("pot"), format_pot(&strings))
+}
+
+// Lays out `strings` as a string table, with the translations from `existing` for strings that still exist.
+fn format_table(strings: &[SourceString], existing: &StringTable, source_locale: &str) -> String {
+    // The source column comes first, followed by any translated locales.
+    let mut locales = vec![source_locale.to_string()];
+    for locale in &existing.locales {
+        if locale != source_locale {
+            locales.push(locale.clone());
+        }
+    }
+    let mut records = Vec::with_capacity(strings.len() + 1);
+    records.push(
+        std::iter::once("keys".to_string())
+            .chain(locales.iter().cloned())
+            .collect::<Vec<_>>(),
+    );
+    for string in strings {
+        let mut record = vec![string.id.clone(), string.text.clone()];
+        for locale in &locales[1..] {
+            record.push(
+                existing
+                    .get(&string.id, locale)
+                    .unwrap_or_default()
+                    .to_string(),
+            );
+        }
+        records.push(record);
+    }
+    format_csv(&records)
+}
+
+fn write_if_changed(path: &Path, source: String) -> Result<()> {
+    if let Ok(old_source) = fs::read_to_string(path) {
+        if source == old_source {
+            return Ok(());
+        }
+    }
+    if let Err(err) = fs::write(path, &source) {
+        return Err(error!(
+            "Error writing string table to '{}': {}",
+            path.display(),
+            err
+        ));
+    }
+    Ok(())
+}
+
+fn format_csv(records: &[Vec<String>]) -> String {
+    let mut csv = String::new();
+    for record in records {
+        let fields: Vec<String> = record
+            .iter()
+            .map(|field| {
+                if field.contains([',', '"', '\n', '\r']) {
+                    format!("\"{}\"", field.replace('"', "\"\""))
+                } else {
+                    field.clone()
+                }
+            })
+            .collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn parse_csv(source: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+fn format_pot(strings: &[SourceString]) -> String {
+    let escape = |text: &str| {
+        text.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    };
+    let mut pot =
+        String::from("msgid \"\"\nmsgstr \"\"\n\"Content-Type: text/plain; charset=UTF-8\\n\"\n");
+    for string in strings {
+        pot.push('\n');
+        let text = string.text.replace('\n', " ");
+        if !string.context.is_empty() {
+            pot.push_str(&format!("#. {}: {}\n", string.context, text));
+        } else {
+            pot.push_str(&format!("#. {}\n", text));
+        }
+        pot.push_str(&format!("msgid \"{}\"\nmsgstr \"\"\n", escape(&string.id)));
+    }
+    pot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(id: &str, text: &str) -> SourceString {
+        SourceString {
+            id: id.to_string(),
+            text: text.to_string(),
+            context: String::new(),
+        }
+    }
+
+    #[test]
+    fn csv_round_trips() {
+        let records = vec![
+            vec!["keys".to_string(), "en".to_string()],
+            vec!["plain".to_string(), "Hello there".to_string()],
+            vec!["comma".to_string(), "Well, hello".to_string()],
+            vec!["quote".to_string(), "She said \"hi\"".to_string()],
+            vec!["newline".to_string(), "First\nSecond".to_string()],
+            vec!["empty".to_string(), String::new()],
+        ];
+        assert_eq!(parse_csv(&format_csv(&records)), records);
+    }
+
+    #[test]
+    fn csv_quotes_only_when_needed() {
+        let records = vec![vec![
+            "plain".to_string(),
+            "a,b".to_string(),
+            "say \"hi\"".to_string(),
+            "line\nbreak".to_string(),
+        ]];
+        assert_eq!(
+            format_csv(&records),
+            "plain,\"a,b\",\"say \"\"hi\"\"\",\"line\nbreak\"\n"
+        );
+    }
+
+    #[test]
+    fn csv_accepts_crlf() {
+        assert_eq!(
+            parse_csv("keys,en\r\nhello,Hello\r\n"),
+            vec![
+                vec!["keys".to_string(), "en".to_string()],
+                vec!["hello".to_string(), "Hello".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn table_keeps_existing_translations() {
+        let existing = StringTable::parse("keys,en,fr\nhello,Hello,Bonjour\nremoved,Gone,Parti\n");
+        let strings = [source("hello", "Hello!"), source("new", "New line")];
+        let table = StringTable::parse(&format_table(&strings, &existing, "en"));
+        assert_eq!(table.locales, ["en", "fr"]);
+        assert_eq!(table.get("hello", "en"), Some("Hello!"));
+        assert_eq!(table.get("hello", "fr"), Some("Bonjour"));
+        assert_eq!(table.get("new", "fr"), None);
+        assert!(!table.rows.contains_key("removed"));
+    }
+
+    #[test]
+    fn table_puts_source_locale_first() {
+        let existing = StringTable::parse("keys,fr,en\nhello,Bonjour,Hello\n");
+        let strings = [source("hello", "Hello")];
+        assert_eq!(
+            format_table(&strings, &existing, "en"),
+            "keys,en,fr\nhello,Hello,Bonjour\n"
+        );
+    }
+
+    fn passage(source: &str) -> Vec<serde_yaml::Value> {
+        serde_yaml::from_str(source).unwrap()
+    }
+
+    #[test]
+    fn strings_skip_command_parameters() {
+        let passage = passage(
+            r#"
+- Narration.
+- May: Hello!
+- May.SetAnimatorTrigger: [drinkcoffee]
+- PlaySound: { sound: door }
+- if coffee:
+    - May: Thanks!
+- choices: { Yes: Accept, No: Decline }
+"#,
+        );
+        let characters = HashSet::from(["May".to_string()]);
+        let is_command = |key: &str| key == "PlaySound" || key.starts_with("May.");
+        let mut found = Vec::new();
+        collect_strings(&passage, 0, &characters, &is_command, &mut found);
+        let texts: Vec<&str> = found.iter().map(|(_, text, _)| text.as_str()).collect();
+        assert_eq!(texts, ["Narration.", "Hello!", "Thanks!", "No", "Yes"]);
+    }
+
+    #[test]
+    fn strings_are_numbered_like_runner_lines() {
+        let passage = passage(
+            r#"
+- May: Hi.
+- if coffee:
+    - May: Thanks!
+  else:
+    - Oh.
+- choices:
+    Yes:
+      - May: Great.
+    No: Decline
+- May: Hi.
+"#,
+        );
+        let characters = HashSet::from(["May".to_string()]);
+        let mut found = Vec::new();
+        let end = collect_strings(&passage, 0, &characters, &|_: &str| false, &mut found);
+        let lines: Vec<(&str, usize)> = found
+            .iter()
+            .map(|(_, text, line)| (text.as_str(), *line))
+            .collect();
+        // Line 1 is the `if`, 3 the break after its first branch and 5 the `choices`.
+        assert_eq!(
+            lines,
+            [
+                ("Hi.", 1),
+                ("Thanks!", 3),
+                ("Oh.", 5),
+                ("No", 5),
+                ("Yes", 5),
+                ("Great.", 7),
+                ("Hi.", 8),
+            ]
+        );
+        assert_eq!(end, 8);
+    }
+}
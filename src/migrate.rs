@@ -0,0 +1,263 @@
+//! Carrying saved bookmarks over to newer versions of the story.
+//!
+//! Saves record a hash of the story they were made with. When a save from a different story is loaded,
+//! renames from the migrations file are applied to it, then it's checked for passages and variables
+//! that no longer exist.
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use godot::prelude::*;
+use kataru::*;
+use serde::{Deserialize, Serialize};
+
+use crate::choices::VISITED_NAMESPACE;
+use crate::convert::{edit_state, serialize_state};
+use crate::lines::content_hash;
+use crate::reload::find_passage;
+use crate::status::*;
+
+/// A hash of the story's content, which changes whenever the story does.
+pub fn story_hash(story: &Story) -> String {
+    // Converting to a value first sorts every map, so the hash doesn't depend on iteration order.
+    match serde_json::to_value(story) {
+        Ok(value) => format!("{:08x}", content_hash(&value.to_string())),
+        Err(_) => String::new(),
+    }
+}
+
+/// The story a save was made with, stored next to its bookmark.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct SaveVersion {
+    pub story_hash: String,
+}
+
+impl SaveVersion {
+    pub fn path_for(bookmark_path: &Path) -> PathBuf {
+        bookmark_path.with_extension("version.json")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let source = match serde_json::to_string(self) {
+            Ok(source) => source,
+            Err(err) => return Err(error!("Could not serialize save version: {}", err)),
+        };
+        if let Err(err) = fs::write(path, source) {
+            return Err(error!(
+                "Error writing save version to '{}': {}",
+                path.display(),
+                err
+            ));
+        }
+        Ok(())
+    }
+
+    /// Loads the save version for the bookmark at `bookmark_path`, or `None` for saves made before versions were recorded.
+    pub fn load_for(bookmark_path: &Path) -> Result<Option<Self>> {
+        let path = Self::path_for(bookmark_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let source = match fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(err) => {
+                return Err(error!(
+                    "Error reading save version from '{}': {}",
+                    path.display(),
+                    err
+                ))
+            }
+        };
+        match serde_json::from_str(&source) {
+            Ok(version) => Ok(Some(version)),
+            Err(err) => Err(error!("Could not parse save version: {}", err)),
+        }
+    }
+}
+
+/// Renames to apply to saves made with older versions of the story, loaded from YAML:
+/// ```yml
+/// passages:
+///   OldPassage: NewPassage
+/// state:
+///   oldVariable: newVariable
+///   namespace:oldVariable: namespace:newVariable
+/// ```
+#[derive(Default, Debug, Deserialize)]
+pub struct Migrations {
+    #[serde(default)]
+    passages: HashMap<String, String>,
+    #[serde(default)]
+    state: HashMap<String, String>,
+}
+
+// Splits a `namespace:name` key, where unqualified names are global.
+fn split_key(key: &str) -> (&str, &str) {
+    key.split_once(':').unwrap_or((kataru::GLOBAL, key))
+}
+
+impl Migrations {
+    pub fn load(path: &Path) -> InterfaceResult<Self> {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) => {
+                return Err(KataruError::new(
+                    ERROR_IO,
+                    format!(
+                        "Error reading migrations from '{}': {}",
+                        path.display(),
+                        err
+                    ),
+                ))
+            }
+        };
+        match serde_yaml::from_str(&source) {
+            Ok(migrations) => Ok(migrations),
+            Err(err) => Err(KataruError::new(
+                ERROR_PARSE,
+                format!("Could not parse migrations: {}", err),
+            )),
+        }
+    }
+
+    /// Applies the renames to `bookmark`.
+    pub fn apply(&self, bookmark: &Bookmark) -> InterfaceResult<Bookmark> {
+        let mut bookmark = edit_state(bookmark, |state| {
+            for (old, new) in &self.state {
+                let (old_namespace, old_name) = split_key(old);
+                let Some(value) = state
+                    .get_mut(old_namespace)
+                    .and_then(|variables| variables.remove(old_name))
+                else {
+                    continue;
+                };
+                let (new_namespace, new_name) = split_key(new);
+                state
+                    .entry(new_namespace.to_string())
+                    .or_default()
+                    .insert(new_name.to_string(), value);
+            }
+        })?;
+        let qualified = format!("{}:{}", bookmark.namespace(), bookmark.passage());
+        let renamed = self
+            .passages
+            .get(bookmark.passage())
+            .or_else(|| self.passages.get(&qualified))
+            .cloned();
+        if let Some(passage) = renamed {
+            bookmark.set_passage(passage);
+        }
+        Ok(bookmark)
+    }
+}
+
+/// What in a bookmark no longer exists in the story.
+#[derive(Default, Debug)]
+pub struct Compatibility {
+    /// The bookmark's passage, if it was removed.
+    pub missing_passage: Option<String>,
+    /// State variables that are no longer declared, as `namespace:name` (or `name` for global ones).
+    pub missing_variables: Vec<String>,
+}
+
+impl Compatibility {
+    pub fn to_dictionary(&self) -> Dictionary {
+        let mut dict = Dictionary::new();
+        dict.insert(
+            "missing_passage",
+            self.missing_passage.clone().unwrap_or_default(),
+        );
+        dict.insert(
+            "missing_variables",
+            Array::<GodotString>::from_iter(self.missing_variables.iter().map(|v| v.into())),
+        );
+        dict
+    }
+}
+
+// Returns true if `variable` is declared, directly or through a `$passage` or `$character` template.
+fn is_declared(declared: impl Fn(&str) -> bool, variable: &str) -> bool {
+    if declared(variable) {
+        return true;
+    }
+    match variable.split_once('.') {
+        Some((_, name)) => ["$passage", "$character"]
+            .iter()
+            .any(|template| declared(&format!("{}.{}", template, name))),
+        None => false,
+    }
+}
+
+/// Checks `bookmark` for a passage or state variables that are missing from `story`.
+pub fn check(bookmark: &Bookmark, story: &Story) -> InterfaceResult<Compatibility> {
+    let mut compatibility = Compatibility::default();
+    if find_passage(story, bookmark.namespace(), bookmark.passage()).is_none() {
+        compatibility.missing_passage = Some(bookmark.passage().to_string());
+    }
+    let (_, state) = serialize_state(bookmark)?;
+    for (namespace, variables) in &state {
+        if namespace == VISITED_NAMESPACE {
+            continue;
+        }
+        for variable in variables.keys() {
+            let declared = story.sections.get(namespace).is_some_and(|section| {
+                is_declared(|name| section.config.state.contains_key(name), variable)
+            });
+            if declared {
+                continue;
+            }
+            compatibility
+                .missing_variables
+                .push(if namespace == kataru::GLOBAL {
+                    variable.clone()
+                } else {
+                    format!("{}:{}", namespace, variable)
+                });
+        }
+    }
+    Ok(compatibility)
+}
+
+// Godot hands every number back as a float, so whole ones are turned back into integers
+// for fields like the bookmark's line.
+fn normalize_numbers(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Number(number) => {
+            if let Some(float) = number.as_f64() {
+                if float.fract() == 0.0 && float.abs() < (1u64 << 53) as f64 {
+                    *value = serde_json::Value::from(float as i64);
+                }
+            }
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(normalize_numbers),
+        serde_json::Value::Object(map) => map.values_mut().for_each(normalize_numbers),
+        _ => {}
+    }
+}
+
+/// Rebuilds a bookmark from the dictionary a migration callback returned.
+pub fn bookmark_from_json(mut value: serde_json::Value) -> InterfaceResult<Bookmark> {
+    normalize_numbers(&mut value);
+    match serde_json::from_value(value) {
+        Ok(bookmark) => Ok(bookmark),
+        Err(err) => Err(KataruError::new(
+            ERROR_VALIDATION,
+            format!("Migration callback returned an invalid bookmark: {}", err),
+        )),
+    }
+}
+
+/// Removes the state variables listed in `compatibility` from `bookmark`.
+pub fn remove_missing_variables(
+    bookmark: &Bookmark,
+    compatibility: &Compatibility,
+) -> InterfaceResult<Bookmark> {
+    edit_state(bookmark, |state| {
+        for variable in &compatibility.missing_variables {
+            let (namespace, name) = split_key(variable);
+            if let Some(variables) = state.get_mut(namespace) {
+                variables.remove(name);
+            }
+        }
+    })
+}
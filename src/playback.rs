@@ -0,0 +1,132 @@
+//! Timers driven from `_process`: auto-play and choice timeouts.
+use kataru::*;
+
+use crate::lines::split_pinned_id;
+
+/// Timing used to decide how long each line stays on screen.
+pub struct AutoplaySettings {
+    pub chars_per_second: f64,
+    pub min_line_time: f64,
+}
+
+impl Default for AutoplaySettings {
+    fn default() -> Self {
+        Self {
+            chars_per_second: 30.0,
+            min_line_time: 1.0,
+        }
+    }
+}
+
+impl AutoplaySettings {
+    /// Seconds to show a line of dialogue with the given text.
+    fn line_time(&self, text: &str) -> f64 {
+        let read_time = if self.chars_per_second > 0.0 {
+            text.chars().count() as f64 / self.chars_per_second
+        } else {
+            0.0
+        };
+        read_time.max(self.min_line_time)
+    }
+}
+
+#[derive(Default)]
+pub struct Autoplay {
+    pub paused: bool,
+    // Seconds until the next line, or `None` while waiting on the player.
+    delay: Option<f64>,
+}
+
+impl Autoplay {
+    /// Schedules the next advance after `line` was emitted.
+    /// Choices and inputs wait on the player, or on their `ChoiceTimeout`.
+    pub fn schedule(&mut self, line: &Line, settings: &AutoplaySettings) {
+        self.delay = match line {
+            Line::Dialogue(dialogue) => Some(settings.line_time(split_pinned_id(&dialogue.text).0)),
+            Line::Command(_) => Some(0.0),
+            _ => None,
+        };
+    }
+
+    /// Schedules the next advance for a line already in the history, when auto-play starts.
+    pub fn schedule_entry(&mut self, kind: &str, text: &str, settings: &AutoplaySettings) {
+        self.delay = match kind {
+            "dialogue" => Some(settings.line_time(text)),
+            "command" => Some(0.0),
+            _ => None,
+        };
+    }
+
+    /// Drops the scheduled advance, so auto-play picks up from the next line the runner emits.
+    pub fn reset(&mut self) {
+        self.delay = None;
+    }
+
+    /// Advances the timer by `delta` seconds, returning true once it's time for the next line.
+    pub fn tick(&mut self, delta: f64) -> bool {
+        if self.paused {
+            return false;
+        }
+        if let Some(delay) = &mut self.delay {
+            *delay -= delta;
+            if *delay <= 0.0 {
+                self.delay = None;
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Cuts the current line short, if it's timed.
+    /// Returns false if the runner is waiting on the player instead.
+    pub fn skip(&mut self) -> bool {
+        if self.delay.is_some() {
+            self.delay = Some(0.0);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// The deadline on a timed set of choices.
+pub struct ChoiceTimeout {
+    pub remaining: f64,
+    choices: Vec<String>,
+    // The choice picked when time runs out, or the first one if unset.
+    default: Option<String>,
+}
+
+impl ChoiceTimeout {
+    /// Returns a deadline for `choices`, or `None` if they aren't timed.
+    pub fn new(choices: &Choices) -> Option<Self> {
+        if choices.timeout <= 0.0 || choices.choices.is_empty() {
+            return None;
+        }
+        Some(Self {
+            remaining: choices.timeout,
+            choices: choices.choices.iter().map(|c| c.to_string()).collect(),
+            default: None,
+        })
+    }
+
+    /// Sets the choice to pick when time runs out. Returns false if it isn't one of the choices.
+    pub fn set_default(&mut self, choice: String) -> bool {
+        if !self.choices.contains(&choice) {
+            return false;
+        }
+        self.default = Some(choice);
+        true
+    }
+
+    /// Advances the deadline by `delta` seconds, returning the choice to submit once it passes.
+    pub fn tick(&mut self, delta: f64) -> Option<String> {
+        self.remaining -= delta;
+        if self.remaining > 0.0 {
+            return None;
+        }
+        self.default
+            .clone()
+            .or_else(|| self.choices.first().cloned())
+    }
+}
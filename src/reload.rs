@@ -0,0 +1,40 @@
+//! Carries live bookmarks over to a reloaded story during hot reload.
+use kataru::*;
+
+/// Looks up the passage named `passage` in `story`, relative to `namespace`.
+/// Passages may also be qualified as `namespace:passage`.
+pub fn find_passage<'a>(story: &'a Story, namespace: &str, passage: &str) -> Option<&'a Passage> {
+    let (namespace, name) = match passage.split_once(':') {
+        Some((namespace, name)) => (namespace, name),
+        None => (namespace, passage),
+    };
+    let in_namespace = story
+        .sections
+        .get(namespace)
+        .and_then(|section| section.passages.get(name));
+    // Unqualified passages fall back to the global namespace.
+    in_namespace.or_else(|| {
+        story
+            .sections
+            .get(kataru::GLOBAL)
+            .and_then(|section| section.passages.get(name))
+    })
+}
+
+/// Remaps `bookmark` onto a reloaded `story`, adding defaults for newly declared state.
+/// The passage and line are kept if the passage still exists and is still long enough,
+/// otherwise the bookmark is moved to the start of `fallback_passage`.
+/// Returns true if the position was preserved.
+pub fn remap_bookmark(bookmark: &mut Bookmark, story: &Story, fallback_passage: &str) -> bool {
+    bookmark.init_state(story);
+
+    let preserved = match find_passage(story, bookmark.namespace(), bookmark.passage()) {
+        Some(passage) => bookmark.line() <= passage.len(),
+        None => false,
+    };
+    if !preserved && !fallback_passage.is_empty() {
+        bookmark.set_passage(fallback_passage.to_string());
+        bookmark.set_line(0);
+    }
+    preserved
+}
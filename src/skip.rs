@@ -0,0 +1,85 @@
+//! Skip mode: fast-forwarding a runner through lines, optionally only ones the player has read.
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use kataru::*;
+use serde::{Deserialize, Serialize};
+
+/// Which lines a runner skips past.
+pub type SkipMode = u8;
+pub const SKIP_OFF: SkipMode = 0;
+pub const SKIP_READ: SkipMode = 1;
+pub const SKIP_ALL: SkipMode = 2;
+
+/// Skipping stops after this many lines in a single tick, in case the story loops.
+pub const MAX_SKIPPED_LINES: usize = 10000;
+
+/// What a runner's current line means for skipping.
+#[derive(Clone, Copy, PartialEq)]
+pub enum LineState {
+    /// Dialogue the player has read before.
+    Read,
+    /// Dialogue the player hasn't read yet.
+    Unread,
+    /// A command, which skipping always passes.
+    Passable,
+    /// Choices, inputs and the end, which wait on the player.
+    Blocking,
+}
+
+impl LineState {
+    pub fn skippable(self, mode: SkipMode) -> bool {
+        match self {
+            LineState::Read | LineState::Passable => mode != SKIP_OFF,
+            LineState::Unread => mode == SKIP_ALL,
+            LineState::Blocking => false,
+        }
+    }
+}
+
+/// Every line the player has seen, by `namespace:passage:line` key.
+/// Shared by all saves, so it's stored in its own file.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct ReadLines {
+    lines: HashSet<String>,
+}
+
+impl ReadLines {
+    /// Marks the line with `key` as read, returning true if it wasn't already.
+    pub fn insert(&mut self, key: String) -> bool {
+        self.lines.insert(key)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let source = match serde_json::to_string(self) {
+            Ok(source) => source,
+            Err(err) => return Err(error!("Could not serialize read lines: {}", err)),
+        };
+        if let Err(err) = fs::write(path, source) {
+            return Err(error!(
+                "Error writing read lines to '{}': {}",
+                path.display(),
+                err
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) => {
+                return Err(error!(
+                    "Error reading read lines from '{}': {}",
+                    path.display(),
+                    err
+                ))
+            }
+        };
+        match serde_json::from_str(&source) {
+            Ok(read_lines) => Ok(read_lines),
+            Err(err) => Err(error!("Could not parse read lines: {}", err)),
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Named save slots: a bookmark and the files saved next to it, plus metadata for save menus.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use godot::prelude::*;
+use kataru::*;
+use serde::{Deserialize, Serialize};
+
+use crate::convert::json_to_variant;
+use crate::history::History;
+use crate::migrate::SaveVersion;
+use crate::status::*;
+
+/// Writes `path` through a temporary file that's renamed over it,
+/// so a crash mid-write never leaves it half written.
+pub fn write_atomic(path: &Path, write: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+    let mut writes = AtomicWrites::default();
+    writes.stage(path, write)?;
+    writes.commit()
+}
+
+/// Writes a set of files through temporary files, only replacing any of them once every one
+/// is written and flushed to disk. Dropping it before `commit` discards the staged files.
+#[derive(Default)]
+pub struct AtomicWrites {
+    // Temporary files and the paths they replace, in the order they're renamed.
+    staged: Vec<(PathBuf, PathBuf)>,
+}
+
+fn sync(path: &Path) -> Result<()> {
+    match fs::File::open(path).and_then(|file| file.sync_all()) {
+        Ok(()) => Ok(()),
+        Err(err) => Err(error!("Error flushing '{}': {}", path.display(), err)),
+    }
+}
+
+impl AtomicWrites {
+    /// Writes what's meant for `path` to a temporary file next to it.
+    pub fn stage(&mut self, path: &Path, write: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+        // Keep the extension last, since it may decide the format that's written.
+        let temp_path = match path.extension() {
+            Some(extension) => path.with_extension(format!("tmp.{}", extension.to_string_lossy())),
+            None => path.with_extension("tmp"),
+        };
+        // Track the file before writing it, so a failed write is cleaned up too.
+        self.staged.push((temp_path.clone(), path.to_path_buf()));
+        write(&temp_path)?;
+        sync(&temp_path)
+    }
+
+    /// Renames every staged file over the one it replaces, in the order they were staged.
+    pub fn commit(mut self) -> Result<()> {
+        let staged = std::mem::take(&mut self.staged);
+        let mut directories = Vec::new();
+        for (index, (temp_path, path)) in staged.iter().enumerate() {
+            if let Err(err) = fs::rename(temp_path, path) {
+                self.staged = staged[index..].to_vec();
+                return Err(error!("Error replacing '{}': {}", path.display(), err));
+            }
+            if let Some(directory) = path.parent() {
+                if !directories.contains(&directory) {
+                    directories.push(directory);
+                }
+            }
+        }
+        // Flush the renames too. Directories can't be opened for this on every platform,
+        // so this is best effort.
+        for directory in directories {
+            let _ = fs::File::open(directory).and_then(|directory| directory.sync_all());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for AtomicWrites {
+    fn drop(&mut self) {
+        for (temp_path, _) in &self.staged {
+            let _ = fs::remove_file(temp_path);
+        }
+    }
+}
+
+/// Slot names become file names, so they can't contain path separators.
+pub fn validate_name(name: &str) -> InterfaceResult<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | ' '));
+    if valid {
+        Ok(())
+    } else {
+        Err(KataruError::new(
+            ERROR_VALIDATION,
+            format!(
+                "Invalid save slot name '{}': use letters, numbers, spaces, '-' and '_'.",
+                name
+            ),
+        ))
+    }
+}
+
+/// Where the slot named `name` keeps its bookmark.
+pub fn bookmark_path(slots_path: &Path, name: &str) -> PathBuf {
+    slots_path.join(format!("{}.yml", name))
+}
+
+/// Seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// What a save menu shows about a slot.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlotMeta {
+    pub name: String,
+    /// When the slot was saved, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub passage: String,
+    /// Seconds played when the slot was saved.
+    pub playtime: f64,
+    /// Whatever the game passed to `save_slot`.
+    pub metadata: serde_json::Value,
+}
+
+impl SlotMeta {
+    pub fn to_dictionary(&self) -> Dictionary {
+        let mut dict = Dictionary::new();
+        dict.insert("name", self.name.clone());
+        dict.insert("timestamp", self.timestamp as i64);
+        dict.insert("passage", self.passage.clone());
+        dict.insert("playtime", self.playtime);
+        dict.insert("metadata", json_to_variant(&self.metadata));
+        dict
+    }
+
+    /// Slot metadata is saved next to the slot's bookmark.
+    pub fn path_for(bookmark_path: &Path) -> PathBuf {
+        bookmark_path.with_extension("meta.json")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let source = match serde_json::to_string(self) {
+            Ok(source) => source,
+            Err(err) => return Err(error!("Could not serialize save slot: {}", err)),
+        };
+        if let Err(err) = fs::write(path, source) {
+            return Err(error!(
+                "Error writing save slot to '{}': {}",
+                path.display(),
+                err
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) => {
+                return Err(error!(
+                    "Error reading save slot from '{}': {}",
+                    path.display(),
+                    err
+                ))
+            }
+        };
+        match serde_json::from_str(&source) {
+            Ok(meta) => Ok(meta),
+            Err(err) => Err(error!("Could not parse save slot: {}", err)),
+        }
+    }
+}
+
+/// Returns every slot in `slots_path`, most recently saved first.
+/// Slots with unreadable metadata are left out.
+pub fn list(slots_path: &Path) -> Result<Vec<SlotMeta>> {
+    if !slots_path.exists() {
+        return Ok(Vec::new());
+    }
+    let entries = match fs::read_dir(slots_path) {
+        Ok(entries) => entries,
+        Err(err) => {
+            return Err(error!(
+                "Error reading save slots from '{}': {}",
+                slots_path.display(),
+                err
+            ))
+        }
+    };
+    let mut slots: Vec<SlotMeta> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.to_string_lossy().ends_with(".meta.json"))
+        .filter_map(|path| SlotMeta::load(&path).ok())
+        .collect();
+    slots.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    Ok(slots)
+}
+
+/// Deletes the slot named `name`. Returns false if there was no such slot.
+pub fn delete(slots_path: &Path, name: &str) -> Result<bool> {
+    let bookmark_path = bookmark_path(slots_path, name);
+    let meta_path = SlotMeta::path_for(&bookmark_path);
+    if !meta_path.exists() {
+        return Ok(false);
+    }
+    // Remove the metadata first, so a partly deleted slot no longer shows up.
+    for path in [
+        meta_path,
+        bookmark_path.clone(),
+        History::path_for(&bookmark_path),
+        SaveVersion::path_for(&bookmark_path),
+    ] {
+        if !path.exists() {
+            continue;
+        }
+        if let Err(err) = fs::remove_file(&path) {
+            return Err(error!("Error deleting '{}': {}", path.display(), err));
+        }
+    }
+    Ok(true)
+}
@@ -0,0 +1,85 @@
+//! In-memory snapshots of a bookmark, for uploading saves as buffers instead of files.
+//!
+//! A snapshot is JSON holding the serialized bookmark and its checksum,
+//! so corrupt buffers are rejected instead of loaded.
+use kataru::*;
+use serde::{Deserialize, Serialize};
+
+use crate::lines::content_hash;
+use crate::status::*;
+
+const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    version: u32,
+    checksum: String,
+    // The story the bookmark was saved with. Missing from snapshots taken before it was recorded.
+    #[serde(default)]
+    story_hash: Option<String>,
+    // Kept as a string so the checksum covers the exact bytes that were written.
+    bookmark: String,
+}
+
+fn checksum(source: &str) -> String {
+    format!("{:08x}", content_hash(source))
+}
+
+/// Serializes `bookmark`, saved with the story hashed as `story_hash`, into a snapshot.
+pub fn to_json(bookmark: &Bookmark, story_hash: &str) -> InterfaceResult<String> {
+    let bookmark = match serde_json::to_string(bookmark) {
+        Ok(bookmark) => bookmark,
+        Err(err) => {
+            return Err(KataruError::new(
+                ERROR_RUNTIME,
+                format!("Could not serialize bookmark: {}", err),
+            ))
+        }
+    };
+    let snapshot = Snapshot {
+        version: SNAPSHOT_VERSION,
+        checksum: checksum(&bookmark),
+        story_hash: Some(story_hash.to_string()),
+        bookmark,
+    };
+    match serde_json::to_string(&snapshot) {
+        Ok(source) => Ok(source),
+        Err(err) => Err(KataruError::new(
+            ERROR_RUNTIME,
+            format!("Could not serialize snapshot: {}", err),
+        )),
+    }
+}
+
+/// Deserializes a bookmark and the hash of the story it was saved with from a snapshot,
+/// checking it wasn't corrupted.
+pub fn from_json(source: &str) -> InterfaceResult<(Bookmark, Option<String>)> {
+    let snapshot: Snapshot = match serde_json::from_str(source) {
+        Ok(snapshot) => snapshot,
+        Err(err) => {
+            return Err(KataruError::new(
+                ERROR_PARSE,
+                format!("Could not parse snapshot: {}", err),
+            ))
+        }
+    };
+    if snapshot.version != SNAPSHOT_VERSION {
+        return Err(KataruError::new(
+            ERROR_VALIDATION,
+            format!("Unsupported snapshot version {}.", snapshot.version),
+        ));
+    }
+    if checksum(&snapshot.bookmark) != snapshot.checksum {
+        return Err(KataruError::new(
+            ERROR_VALIDATION,
+            "Snapshot checksum does not match, so it is corrupt.",
+        ));
+    }
+    match serde_json::from_str(&snapshot.bookmark) {
+        Ok(bookmark) => Ok((bookmark, snapshot.story_hash)),
+        Err(err) => Err(KataruError::new(
+            ERROR_PARSE,
+            format!("Could not parse snapshot bookmark: {}", err),
+        )),
+    }
+}
@@ -0,0 +1,70 @@
+//! Error kinds reported to GDScript by `KataruInterface`.
+use std::fmt;
+use std::path::Path;
+
+pub type ErrorKind = u8;
+pub const ERROR_NONE: ErrorKind = 0;
+pub const ERROR_UNINITIALIZED: ErrorKind = 1;
+pub const ERROR_PARSE: ErrorKind = 2;
+pub const ERROR_VALIDATION: ErrorKind = 3;
+pub const ERROR_IO: ErrorKind = 4;
+pub const ERROR_RUNTIME: ErrorKind = 5;
+
+/// A Kataru error tagged with the kind of failure, so GDScript can react to it.
+#[derive(Debug)]
+pub struct KataruError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl KataruError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub fn uninitialized() -> Self {
+        Self::new(ERROR_UNINITIALIZED, "Kataru was not initialized.")
+    }
+}
+
+impl fmt::Display for KataruError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Errors coming straight out of kataru are runtime errors unless the call site tags them
+/// with a more specific kind through `WithKind::with_kind`.
+impl From<kataru::Error> for KataruError {
+    fn from(err: kataru::Error) -> Self {
+        Self::new(ERROR_RUNTIME, err.to_string())
+    }
+}
+
+pub type InterfaceResult<T> = std::result::Result<T, KataruError>;
+
+/// Fails with `ERROR_IO` if there is no file at `path`, so errors from loading it can be tagged as parse errors.
+pub fn require_file(path: &Path) -> InterfaceResult<()> {
+    if path.is_file() {
+        Ok(())
+    } else {
+        Err(KataruError::new(
+            ERROR_IO,
+            format!("No file at '{}'.", path.display()),
+        ))
+    }
+}
+
+/// Tags a kataru result with the kind of error it produces.
+pub trait WithKind<T> {
+    fn with_kind(self, kind: ErrorKind) -> InterfaceResult<T>;
+}
+
+impl<T> WithKind<T> for kataru::Result<T> {
+    fn with_kind(self, kind: ErrorKind) -> InterfaceResult<T> {
+        self.map_err(|err| KataruError::new(kind, err.to_string()))
+    }
+}
@@ -0,0 +1,121 @@
+//! Watches the story source directory for changes without blocking the main thread.
+//!
+//! Filesystem events are delivered by `notify` on its own thread and queued on a channel.
+//! `StoryWatcher::poll` drains the queue from `_process` and only reports changes once
+//! no new events have arrived for the debounce interval, so a burst of writes from an
+//! editor triggers a single recompile.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::time::{Duration, Instant};
+
+use kataru::*;
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Removed,
+    Renamed,
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Modified => "modified",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Renamed => "renamed",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+pub struct StoryWatcher {
+    // Kept alive so that events keep being sent.
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    // Changes seen since the last reload, by path.
+    pending: BTreeMap<PathBuf, ChangeKind>,
+    last_event: Option<Instant>,
+}
+
+impl StoryWatcher {
+    /// Starts watching `dir` recursively.
+    pub fn new(dir: &Path) -> Result<Self> {
+        let (sender, events) = channel();
+        let mut watcher = match notify::recommended_watcher(sender) {
+            Ok(watcher) => watcher,
+            Err(err) => return Err(error!("Could not create story watcher: {}", err)),
+        };
+        if let Err(err) = watcher.watch(dir, RecursiveMode::Recursive) {
+            return Err(error!("Could not watch '{}': {}", dir.display(), err));
+        }
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            pending: BTreeMap::new(),
+            last_event: None,
+        })
+    }
+
+    /// Drains queued events. Returns the changed files once no event has arrived for `debounce`.
+    pub fn poll(&mut self, debounce: Duration) -> Result<Option<Vec<(PathBuf, ChangeKind)>>> {
+        loop {
+            match self.events.try_recv() {
+                Ok(Ok(event)) => self.queue(event),
+                Ok(Err(err)) => return Err(error!("Story watcher error: {}", err)),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    return Err(error!("Story watcher stopped unexpectedly."))
+                }
+            }
+        }
+
+        match self.last_event {
+            Some(last_event) if last_event.elapsed() >= debounce && !self.pending.is_empty() => {
+                self.last_event = None;
+                Ok(Some(
+                    std::mem::take(&mut self.pending).into_iter().collect(),
+                ))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn queue(&mut self, event: Event) {
+        let kind = match event.kind {
+            EventKind::Create(_) => ChangeKind::Added,
+            EventKind::Remove(_) => ChangeKind::Removed,
+            EventKind::Modify(ModifyKind::Name(_)) => ChangeKind::Renamed,
+            EventKind::Modify(_) => ChangeKind::Modified,
+            // Reads and unknown events don't change the story.
+            _ => return,
+        };
+        for path in event.paths {
+            if kind != ChangeKind::Removed && !is_story_path(&path) {
+                continue;
+            }
+            let kind = match (self.pending.get(&path), kind) {
+                // A file created and then written is still just added.
+                (Some(ChangeKind::Added), ChangeKind::Modified) => ChangeKind::Added,
+                _ => kind,
+            };
+            self.pending.insert(path, kind);
+            self.last_event = Some(Instant::now());
+        }
+    }
+}
+
+// Only YAML files are part of the story, which skips editor swap and backup files.
+// Directories are kept too, since creating or renaming one moves the files in it.
+// A renamed directory may already be gone, so paths without an extension count as directories.
+fn is_story_path(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => ext == "yml" || ext == "yaml" || path.is_dir(),
+        None => true,
+    }
+}